@@ -9,13 +9,26 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use plugins::splash::GameState;
 
+use crate::plugins::altimeter::AltimeterPlugin;
 use crate::plugins::camera::CameraPlugin;
+use crate::plugins::daylight::DaylightPlugin;
+use crate::plugins::dust::DustPlugin;
 use crate::plugins::environment::EnvironmentPlugin;
+use crate::plugins::exhaust::ExhaustPlugin;
+use crate::plugins::gravity::GravityPlugin;
+use crate::plugins::ground_control::GroundControlPlugin;
+use crate::plugins::hud::HudPlugin;
 use crate::plugins::landing_compass::LandingCompassPlugin;
+use crate::plugins::menu::MenuPlugin;
+use crate::plugins::metrics::MetricsPlugin;
+use crate::plugins::mission::MissionPlugin;
 use crate::plugins::rocket::RocketPlugin;
+use crate::plugins::sas::SasPlugin;
+use crate::plugins::settings::{Settings, SettingsPlugin};
 use crate::plugins::splash::SplashPlugin;
-// use crate::plugins::telemetry::TelemetryPlugin;
+use crate::plugins::telemetry::{TelemetryPlugin, TelemetrySettings};
 use crate::plugins::terrain::TerrainPlugin;
+use crate::plugins::trajectory::TrajectoryPlugin;
 use crate::plugins::weather::MarsWeather;
 use crate::plugins::weather::WeatherPlugin;
 
@@ -23,10 +36,11 @@ mod plugins;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 3)]
 async fn main() {
+    let settings = Settings::load();
     let _res = MarsWeather::get().await;
 
-    App::new()
-        .register_type::<DMat3>()
+    let mut app = App::new();
+    app.register_type::<DMat3>()
         // External plugins
         .add_plugins((
             DefaultPlugins.set(WindowPlugin {
@@ -41,33 +55,49 @@ async fn main() {
         ))
         .add_systems(Update, rapier_context_system)
         // Internal plugins
+        .add_plugins(SettingsPlugin {
+            settings: settings.clone(),
+        })
+        .add_plugins(MissionPlugin)
         .add_plugins(SplashPlugin)
+        .add_plugins(MenuPlugin)
         .add_plugins(EnvironmentPlugin)
+        .add_plugins(DaylightPlugin)
         .add_plugins(TerrainPlugin)
+        .add_plugins(GravityPlugin)
         .add_plugins(RocketPlugin)
+        .add_plugins(SasPlugin)
+        .add_plugins(AltimeterPlugin)
+        .add_plugins(ExhaustPlugin)
         .add_plugins(CameraPlugin)
+        .add_plugins(TrajectoryPlugin)
         .add_plugins(LandingCompassPlugin)
-        // .add_plugins(TelemetryPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(TelemetryPlugin {
+            address: settings.network.telemetry_address.clone(),
+            settings: TelemetrySettings {
+                rate_hz: settings.network.telemetry_rate_hz,
+                backpressure: settings.network.telemetry_backpressure,
+            },
+        })
+        .add_plugins(GroundControlPlugin {
+            address: settings.network.ground_control_address.clone(),
+        })
         .add_plugins(WeatherPlugin {
             weather: _res.unwrap(),
         })
-        .insert_resource(RapierConfiguration {
-            gravity: Vec3::new(0.0, -3.71, 0.0),
-            ..RapierConfiguration::new(1.0)
-        })
-        .run();
+        .add_plugins(DustPlugin);
+
+    if let Some(address) = settings.network.metrics_address {
+        app.add_plugins(MetricsPlugin { address });
+    }
+
+    app.run();
 }
 
 fn rapier_context_system(
     mut rapier_config: ResMut<RapierConfiguration>,
     game_state: ResMut<State<GameState>>,
 ) {
-    match game_state.get() {
-        GameState::Playing => {
-            rapier_config.physics_pipeline_active = true;
-        }
-        GameState::Paused => {
-            rapier_config.physics_pipeline_active = false;
-        }
-    }
+    rapier_config.physics_pipeline_active = game_state.get().is_flying();
 }
@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use bevy_rapier3d::{dynamics::Velocity as BodyVelocity, prelude::*};
+
+use super::{
+    gravity::PlanetGravity,
+    rocket::{Rocket, RocketCollider},
+    weather::Dust,
+};
+
+/// Furthest the radar looks for ground.
+pub const RADAR_RANGE: f32 = 100.0;
+// the antenna sits on the bottom of the rocket, half its height below the centre
+const ANTENNA_OFFSET: f32 = 0.2;
+// largest radar error in a full dust storm
+const DUST_NOISE: f32 = 0.6;
+
+/// Height above whatever ground is directly below the rocket, measured by
+/// casting a ray against the terrain colliders.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct RadarAltimeter {
+    /// `None` when there is no ground within `RADAR_RANGE`.
+    pub height: Option<f32>,
+    pub ground_normal: Vec3,
+    /// Steepness of the ground under the rocket.
+    pub slope_degrees: f32,
+    /// Seconds until touchdown at the current sink rate.
+    pub time_to_impact: Option<f32>,
+    /// Seconds until touchdown if the engines cut out now.
+    pub time_to_impact_unpowered: Option<f32>,
+}
+
+pub struct AltimeterPlugin;
+
+impl Plugin for AltimeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, radar_altimeter_system);
+    }
+}
+
+fn radar_altimeter_system(
+    rapier_context: Res<RapierContext>,
+    gravity: Res<PlanetGravity>,
+    dust: Res<Dust>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    mut altimeter_query: Query<&mut RadarAltimeter, With<Rocket>>,
+) {
+    let Ok((transform, velocity)) = body.get_single() else {
+        return;
+    };
+
+    let position = transform.translation;
+    let up = gravity.up_at(position);
+    let hit = rapier_context.cast_ray_and_get_normal(
+        position,
+        -up,
+        RADAR_RANGE,
+        true,
+        QueryFilter::only_fixed(),
+    );
+
+    for mut altimeter in altimeter_query.iter_mut() {
+        let Some((_, hit)) = hit else {
+            *altimeter = RadarAltimeter::default();
+            continue;
+        };
+
+        let height = (hit.point.distance(position) - ANTENNA_OFFSET
+            + dust.sensor_noise(DUST_NOISE))
+        .max(0.0);
+        let sink_rate = -velocity.linvel.dot(up);
+        let g = gravity.surface_gravity;
+
+        altimeter.height = Some(height);
+        altimeter.ground_normal = hit.normal;
+        altimeter.slope_degrees = hit.normal.angle_between(up).to_degrees();
+        altimeter.time_to_impact = (sink_rate > 0.0).then(|| height / sink_rate);
+        // height = sink_rate * t + g * t^2 / 2
+        altimeter.time_to_impact_unpowered =
+            (g > 0.0).then(|| (-sink_rate + (sink_rate * sink_rate + 2.0 * g * height).sqrt()) / g);
+    }
+}
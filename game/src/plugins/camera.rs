@@ -1,61 +1,353 @@
-use bevy::prelude::*;
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    log,
+    prelude::*,
+};
+
+use bevy_rapier3d::{dynamics::Velocity as BodyVelocity, prelude::*};
 
 use super::{
-    rocket::{Rocket, START_ALTITUDE},
-    splash::GameState,
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    mission::{ActiveMission, TargetPad},
+    rocket::{FlightEvent, Rocket, RocketCollider, Thrust, START_ALTITUDE},
+    settings::Settings,
+    splash::in_flight,
 };
 
 pub struct CameraPlugin;
 
 const CAMERA_OFFSET: Vec3 = Vec3::new(0.0, 0.5, 12.0);
+// how much of the rocket's attitude the chase camera swings with
+const CHASE_ATTITUDE_FOLLOW: f32 = 0.5;
+// time the follow spring takes to settle most of the way
+const FOLLOW_SMOOTH_TIME: f32 = 0.35;
+// seconds of travel the camera leads the rocket by
+const LOOK_AHEAD_TIME: f32 = 0.6;
+const MAX_LOOK_AHEAD: f32 = 3.0;
+// how close the camera may get to the ground
+const CAMERA_CLEARANCE: f32 = 0.3;
+const MAX_SHAKE_OFFSET: f32 = 0.15;
+const MAX_SHAKE_ANGLE: f32 = 0.02;
+const SHAKE_DECAY: f32 = 1.5;
+// full thrust keeps this much trauma going
+const THRUST_TRAUMA: f32 = 0.35;
+const TOUCHDOWN_TRAUMA_PER_SPEED: f32 = 0.25;
+const ORBIT_SENSITIVITY: f32 = 0.005;
+const ORBIT_MIN_DISTANCE: f32 = 2.0;
+const ORBIT_MAX_DISTANCE: f32 = 30.0;
+const COCKPIT_OFFSET: Vec3 = Vec3::new(0.0, 0.3, 0.0);
+const TOP_DOWN_HEIGHT: f32 = 20.0;
+const GROUND_CAMERA_OFFSET: Vec3 = Vec3::new(4.0, 1.5, 4.0);
+
+/// Ways of looking at the rocket, cycled with C by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Behind the rocket, swinging with its attitude.
+    #[default]
+    Chase,
+    /// Free orbit around the rocket, dragged with the mouse.
+    Orbit,
+    /// From the top of the rocket, looking out.
+    Cockpit,
+    /// Straight down from above, for lining up with the pad.
+    TopDown,
+    /// Fixed next to the target pad, watching the rocket come in.
+    Ground,
+}
+
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            CameraMode::Chase => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Cockpit,
+            CameraMode::Cockpit => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Ground,
+            CameraMode::Ground => CameraMode::Chase,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraControl {
+    pub mode: CameraMode,
+    pub orbit_yaw: f32,
+    pub orbit_pitch: f32,
+    pub orbit_distance: f32,
+}
+
+impl Default for CameraControl {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::default(),
+            orbit_yaw: 0.0,
+            orbit_pitch: 0.1,
+            orbit_distance: CAMERA_OFFSET.length(),
+        }
+    }
+}
+
+/// Smoothed camera state, kept apart from the shake added on top of it.
+#[derive(Component, Default)]
+pub struct CameraRig {
+    position: Vec3,
+    velocity: Vec3,
+    /// 0 to 1, shake strength grows with its square.
+    pub trauma: f32,
+    shake_time: f32,
+}
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup);
-        app.add_systems(
-            Update,
-            (follow_rocket_system).run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<CameraControl>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    switch_camera_mode_system,
+                    orbit_input_system,
+                    camera_trauma_system.run_if(in_flight),
+                    follow_rocket_system,
+                )
+                    .chain(),
+            );
     }
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn(((
+fn setup(mut commands: Commands, settings: Res<Settings>) {
+    let start = Vec3::new(
+        CAMERA_OFFSET.x,
+        START_ALTITUDE + CAMERA_OFFSET.y,
+        CAMERA_OFFSET.z,
+    );
+
+    commands.spawn((
         Camera3dBundle {
             projection: PerspectiveProjection {
                 // We must specify the FOV in radians.
                 // Rust can convert degrees to radians for us.
-                fov: 55.0_f32.to_radians(),
+                fov: settings.graphics.fov_degrees.to_radians(),
                 far: 50.0,
                 ..default()
             }
             .into(),
-            transform: Transform::from_xyz(
-                CAMERA_OFFSET.x,
-                START_ALTITUDE + CAMERA_OFFSET.y,
-                CAMERA_OFFSET.z,
-            )
-            .looking_at(Vec3::new(0.0, 1.5 + START_ALTITUDE, -3.0), Vec3::Y),
+            transform: Transform::from_translation(start)
+                .looking_at(Vec3::new(0.0, 1.5 + START_ALTITUDE, -3.0), Vec3::Y),
             ..default()
         },
         FogSettings {
-            // the color of fog should be purple
-            // color: Color::rgba(0.89, 0.51, 0.27, 1.0),
-            color: Color::srgba(0.5, 0.0, 0.5, 1.0),
+            // purple by default, see `Settings`
+            color: Color::srgb(
+                settings.graphics.fog_color[0],
+                settings.graphics.fog_color[1],
+                settings.graphics.fog_color[2],
+            ),
             directional_light_color: Color::srgba(1.0, 0.85, 0.85, 0.5),
             directional_light_exponent: 30.0,
             falloff: FogFalloff::ExponentialSquared { density: 0.008 },
         },
-    ),));
+        CameraRig {
+            position: start,
+            ..default()
+        },
+    ));
+}
+
+fn switch_camera_mode_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut control: ResMut<CameraControl>,
+) {
+    if keyboard_input.just_pressed(settings.controls.camera) {
+        control.mode = control.mode.next();
+        log::info!("Camera mode: {:?}", control.mode);
+    }
+}
+
+fn orbit_input_system(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut control: ResMut<CameraControl>,
+) {
+    let motion: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    let scroll: f32 = mouse_wheel.read().map(|wheel| wheel.y).sum();
+
+    if control.mode != CameraMode::Orbit {
+        return;
+    }
+
+    if mouse_buttons.pressed(MouseButton::Left) || mouse_buttons.pressed(MouseButton::Right) {
+        control.orbit_yaw -= motion.x * ORBIT_SENSITIVITY;
+        control.orbit_pitch = (control.orbit_pitch + motion.y * ORBIT_SENSITIVITY).clamp(-1.4, 1.4);
+    }
+    control.orbit_distance =
+        (control.orbit_distance - scroll).clamp(ORBIT_MIN_DISTANCE, ORBIT_MAX_DISTANCE);
+}
+
+/// Where the camera wants to be and what it looks at for the current mode.
+/// `look_ahead` shifts the view toward where the rocket is heading.
+fn camera_target(
+    control: &CameraControl,
+    rocket: &Transform,
+    up: Vec3,
+    pad_position: Vec3,
+    look_ahead: Vec3,
+) -> Transform {
+    let position = rocket.translation + look_ahead;
+
+    match control.mode {
+        CameraMode::Chase => {
+            let attitude = Quat::IDENTITY.slerp(rocket.rotation, CHASE_ATTITUDE_FOLLOW);
+            Transform::from_translation(position + attitude * CAMERA_OFFSET)
+                .looking_at(position + up * CAMERA_OFFSET.y, up)
+        }
+        CameraMode::Orbit => {
+            let rotation = Quat::from_rotation_y(control.orbit_yaw)
+                * Quat::from_rotation_x(-control.orbit_pitch);
+            Transform::from_translation(position + rotation * Vec3::Z * control.orbit_distance)
+                .looking_at(position, up)
+        }
+        CameraMode::Cockpit => {
+            // look out over the nose, tipped down toward the ground
+            Transform::from_translation(rocket.translation + rocket.rotation * COCKPIT_OFFSET)
+                .with_rotation(rocket.rotation * Quat::from_rotation_x(-30.0_f32.to_radians()))
+        }
+        CameraMode::TopDown => Transform::from_translation(position + up * TOP_DOWN_HEIGHT)
+            .looking_at(position, Vec3::NEG_Z),
+        CameraMode::Ground => Transform::from_translation(pad_position + GROUND_CAMERA_OFFSET)
+            .looking_at(position, up),
+    }
+}
+
+/// Critically damped spring toward `target`, after Game Programming Gems 4.
+fn smooth_damp(
+    current: Vec3,
+    target: Vec3,
+    velocity: &mut Vec3,
+    smooth_time: f32,
+    delta: f32,
+) -> Vec3 {
+    let omega = 2.0 / smooth_time;
+    let x = omega * delta;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + omega * change) * delta;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (change + temp) * decay
+}
+
+fn camera_trauma_system(
+    time: Res<Time>,
+    difficulty: Res<ActiveDifficulty>,
+    mut flight_events: EventReader<FlightEvent>,
+    rocket_query: Query<&Thrust, With<Rocket>>,
+    mut rig_query: Query<&mut CameraRig>,
+) {
+    let impact: f32 = flight_events
+        .read()
+        .map(|event| match event {
+            FlightEvent::Touchdown { speed } => speed * TOUCHDOWN_TRAUMA_PER_SPEED,
+            _ => 0.0,
+        })
+        .sum();
+    let engine = rocket_query
+        .get_single()
+        .map(|thrust| thrust.value / difficulty.0.max_thrust * THRUST_TRAUMA)
+        .unwrap_or_default();
+
+    for mut rig in rig_query.iter_mut() {
+        rig.trauma = (rig.trauma - SHAKE_DECAY * time.delta_seconds()).max(engine);
+        rig.trauma = (rig.trauma + impact).min(1.0);
+        rig.shake_time += time.delta_seconds();
+    }
 }
 
 fn follow_rocket_system(
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
-    mut rocket_query: Query<&Transform, (With<Rocket>, Without<Camera3d>)>,
+    time: Res<Time>,
+    control: Res<CameraControl>,
+    gravity: Res<PlanetGravity>,
+    mission: Res<ActiveMission>,
+    target: Res<TargetPad>,
+    rapier_context: Res<RapierContext>,
+    mut camera_query: Query<(&mut Transform, &mut CameraRig), With<Camera3d>>,
+    rocket_query: Query<&Transform, (With<Rocket>, Without<Camera3d>)>,
+    body: Query<&BodyVelocity, With<RocketCollider>>,
 ) {
-    for mut camera_transform in camera_query.iter_mut() {
-        for rocket_transform in rocket_query.iter_mut() {
-            camera_transform.translation = rocket_transform.translation + CAMERA_OFFSET;
+    let Ok(rocket_transform) = rocket_query.get_single() else {
+        return;
+    };
+    let up = gravity.up_at(rocket_transform.translation);
+    let pad_position = target
+        .spec(&mission.0)
+        .map(|pad| pad.position)
+        .unwrap_or_default();
+    let look_ahead = body
+        .get_single()
+        .map(|velocity| (velocity.linvel * LOOK_AHEAD_TIME).clamp_length_max(MAX_LOOK_AHEAD))
+        .unwrap_or_default();
+    let wanted = camera_target(&control, rocket_transform, up, pad_position, look_ahead);
+    let focus = rocket_transform.translation + look_ahead;
+
+    for (mut camera_transform, mut rig) in camera_query.iter_mut() {
+        let mut position = match control.mode {
+            CameraMode::Chase | CameraMode::TopDown => {
+                let mut velocity = rig.velocity;
+                let position = smooth_damp(
+                    rig.position,
+                    wanted.translation,
+                    &mut velocity,
+                    FOLLOW_SMOOTH_TIME,
+                    time.delta_seconds(),
+                );
+                rig.velocity = velocity;
+                position
+            }
+            _ => {
+                rig.velocity = Vec3::ZERO;
+                wanted.translation
+            }
+        };
+
+        // keep terrain between the camera and the rocket from hiding it
+        if control.mode != CameraMode::Cockpit {
+            let offset = position - focus;
+            let distance = offset.length();
+            if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+                focus,
+                offset.normalize_or_zero(),
+                distance,
+                true,
+                QueryFilter::only_fixed(),
+            ) {
+                position = hit.point + hit.normal * CAMERA_CLEARANCE;
+            }
         }
+        rig.position = position;
+
+        let rotation = if control.mode == CameraMode::Cockpit {
+            wanted.rotation
+        } else if control.mode == CameraMode::TopDown {
+            Transform::from_translation(position)
+                .looking_at(focus, Vec3::NEG_Z)
+                .rotation
+        } else {
+            Transform::from_translation(position)
+                .looking_at(focus, up)
+                .rotation
+        };
+
+        // shake is layered on top so it never feeds back into the spring
+        let shake = rig.trauma * rig.trauma;
+        let t = rig.shake_time;
+        let jitter = Vec3::new(
+            (t * 37.0).sin() + (t * 61.0).sin() * 0.5,
+            (t * 43.0).sin() + (t * 71.0).sin() * 0.5,
+            (t * 53.0).sin() * 0.5,
+        ) / 1.5;
+
+        camera_transform.translation = position + jitter * shake * MAX_SHAKE_OFFSET;
+        camera_transform.rotation =
+            rotation * Quat::from_rotation_z(jitter.z * shake * MAX_SHAKE_ANGLE);
     }
 }
@@ -0,0 +1,259 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::prelude::*;
+use serde_derive::Deserialize;
+
+use super::{
+    environment::{Sky, Star, StarAssets, Sun},
+    mission::{ActiveMission, MissionStarted},
+    settings::Settings,
+    splash::GameState,
+    weather::Dust,
+};
+
+// a sol lasts 88775 s, split into 24 Mars hours
+const MARS_HOUR_SECONDS: f32 = 88775.0 / 24.0;
+const MARS_OBLIQUITY_DEGREES: f32 = 25.19;
+const DEFAULT_LOCAL_TIME: f32 = 10.0;
+const SUN_DISTANCE: f32 = 40.0;
+const NOON_ILLUMINANCE: f32 = 3000.0;
+// share of the sunlight a full dust storm blocks
+const STORM_DIMMING: f32 = 0.75;
+const DAY_AMBIENT: f32 = 80.0;
+const NIGHT_AMBIENT: f32 = 10.0;
+const DAY_LIGHT_COLOR: Srgba = Srgba::rgb(0.98, 0.95, 0.82);
+// Martian sunsets are blue
+const TWILIGHT_LIGHT_COLOR: Srgba = Srgba::rgb(0.55, 0.65, 0.95);
+const NIGHT_SKY_COLOR: Srgba = Srgba::rgb(0.0, 0.0, 0.0);
+
+/// Time of day and where on Mars the mission takes place.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Daylight {
+    /// Local solar time in Mars hours, 0 to 24. Missions without one carry
+    /// on from the time the last one ended.
+    pub local_time: Option<f32>,
+    pub latitude_degrees: f32,
+    /// Mars' position around the sun, 0 at the northern spring equinox.
+    pub solar_longitude_degrees: f32,
+    /// How much faster than real time the sun moves.
+    pub time_scale: f32,
+}
+
+impl Default for Daylight {
+    fn default() -> Self {
+        Self {
+            local_time: None,
+            // Jezero crater
+            latitude_degrees: 18.4,
+            solar_longitude_degrees: 0.0,
+            time_scale: 60.0,
+        }
+    }
+}
+
+/// Local solar time at the landing site.
+#[derive(Resource)]
+pub struct SolarClock {
+    pub local_time: f32,
+    pub latitude_degrees: f32,
+    pub solar_longitude_degrees: f32,
+    pub time_scale: f32,
+}
+
+impl Default for SolarClock {
+    fn default() -> Self {
+        let daylight = Daylight::default();
+        Self {
+            local_time: DEFAULT_LOCAL_TIME,
+            latitude_degrees: daylight.latitude_degrees,
+            solar_longitude_degrees: daylight.solar_longitude_degrees,
+            time_scale: daylight.time_scale,
+        }
+    }
+}
+
+impl SolarClock {
+    /// Sun elevation above the horizon and azimuth east of north, in radians.
+    pub fn sun_position(&self) -> (f32, f32) {
+        let latitude = self.latitude_degrees.to_radians();
+        let declination = (MARS_OBLIQUITY_DEGREES.to_radians().sin()
+            * self.solar_longitude_degrees.to_radians().sin())
+        .asin();
+        let hour_angle = self.hour_angle();
+
+        let elevation = (latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos())
+        .asin();
+        let azimuth = (-hour_angle.sin())
+            .atan2(declination.tan() * latitude.cos() - latitude.sin() * hour_angle.cos());
+        (elevation, azimuth)
+    }
+
+    /// Sun's hour angle, 0 at local noon.
+    fn hour_angle(&self) -> f32 {
+        (self.local_time - 12.0) / 24.0 * TAU
+    }
+
+    /// Local sidereal time as an angle: the right ascension on the meridian.
+    pub fn sidereal_angle(&self) -> f32 {
+        let longitude = self.solar_longitude_degrees.to_radians();
+        let sun_right_ascension =
+            (MARS_OBLIQUITY_DEGREES.to_radians().cos() * longitude.sin()).atan2(longitude.cos());
+        self.hour_angle() + sun_right_ascension
+    }
+
+    /// Turns the celestial sphere, pole along +Y, into the local sky: the
+    /// pole sits above the northern horizon at the latitude, and the right
+    /// ascension on the meridian faces south.
+    pub fn sky_rotation(&self) -> Quat {
+        let latitude = self.latitude_degrees.to_radians();
+        Quat::from_rotation_x(latitude - FRAC_PI_2)
+            * Quat::from_rotation_y(-FRAC_PI_2 - self.sidereal_angle())
+    }
+
+    /// Unit vector towards the sun, with north along -Z and east along +X.
+    pub fn sun_direction(&self) -> Vec3 {
+        let (elevation, azimuth) = self.sun_position();
+        Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            -azimuth.cos() * elevation.cos(),
+        )
+    }
+}
+
+pub struct DaylightPlugin;
+
+impl Plugin for DaylightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SolarClock>().add_systems(
+            Update,
+            (
+                apply_mission_daylight_system,
+                solar_clock_system.run_if(in_state(GameState::Playing)),
+                daylight_system,
+                starfield_rotation_system,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn apply_mission_daylight_system(
+    mut mission_started: EventReader<MissionStarted>,
+    mission: Res<ActiveMission>,
+    mut clock: ResMut<SolarClock>,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+
+    let daylight = &mission.0.daylight;
+    if let Some(local_time) = daylight.local_time {
+        clock.local_time = local_time.rem_euclid(24.0);
+    }
+    clock.latitude_degrees = daylight.latitude_degrees;
+    clock.solar_longitude_degrees = daylight.solar_longitude_degrees;
+    clock.time_scale = daylight.time_scale;
+}
+
+fn solar_clock_system(time: Res<Time>, mut clock: ResMut<SolarClock>) {
+    let hours = time.delta_seconds() * clock.time_scale / MARS_HOUR_SECONDS;
+    clock.local_time = (clock.local_time + hours).rem_euclid(24.0);
+}
+
+/// Moves the sun and fades the sky, ambient light and stars with it.
+fn daylight_system(
+    clock: Res<SolarClock>,
+    settings: Res<Settings>,
+    dust: Res<Dust>,
+    star_assets: Option<Res<StarAssets>>,
+    mut ambient: ResMut<AmbientLight>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut sun: Query<(&mut DirectionalLight, &mut Transform), With<Sun>>,
+    sky: Query<(&Sky, &Handle<StandardMaterial>)>,
+    new_sky: Query<(), Added<Sky>>,
+) {
+    if !clock.is_changed() && !settings.is_changed() && !dust.is_changed() && new_sky.is_empty() {
+        return;
+    }
+
+    let direction = clock.sun_direction();
+    let height = direction.y;
+    // the sun only lights the ground while it is up; the sky and ambient
+    // light carry twilight until it is 6° below
+    let sunlight = smoothstep(0.0, 0.25, height);
+    let day = smoothstep(-0.1, 0.25, height);
+    let warmth = smoothstep(0.0, 0.3, height);
+
+    for (mut light, mut transform) in sun.iter_mut() {
+        let up = if height.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        *transform =
+            Transform::from_translation(direction * SUN_DISTANCE).looking_to(-direction, up);
+        light.illuminance = NOON_ILLUMINANCE * sunlight * (1.0 - STORM_DIMMING * dust.opacity);
+        light.color = TWILIGHT_LIGHT_COLOR.mix(&DAY_LIGHT_COLOR, warmth).into();
+        light.shadows_enabled = settings.graphics.quality.shadows() && height > 0.0;
+    }
+
+    ambient.brightness = NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * day;
+
+    // materials are only touched when they change, as every write has them
+    // prepared for the GPU again
+    for (sky, handle) in sky.iter() {
+        let color: Color = NIGHT_SKY_COLOR.mix(&sky.tint, day).into();
+        if materials
+            .get(handle)
+            .is_some_and(|material| material.base_color != color)
+        {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color = color;
+            }
+        }
+    }
+
+    // stars come out as the sky darkens
+    if let Some(star_assets) = star_assets {
+        let brightness = 1.0 - smoothstep(-0.15, 0.05, height);
+        let color = Color::srgba(1.0, 1.0, 1.0, brightness);
+        if materials
+            .get(&star_assets.material)
+            .is_some_and(|material| material.base_color != color)
+        {
+            if let Some(material) = materials.get_mut(&star_assets.material) {
+                material.base_color = color;
+                material.emissive = LinearRgba::rgb(brightness, brightness, brightness);
+                material.alpha_mode = if brightness < 1.0 {
+                    AlphaMode::Blend
+                } else {
+                    AlphaMode::Opaque
+                };
+            }
+        }
+    }
+}
+
+/// Turns the stars with the planet.
+fn starfield_rotation_system(
+    clock: Res<SolarClock>,
+    mut stars: Query<&mut Transform, With<Star>>,
+    new_stars: Query<(), Added<Star>>,
+) {
+    if !clock.is_changed() && new_stars.is_empty() {
+        return;
+    }
+
+    let rotation = clock.sky_rotation();
+    for mut transform in stars.iter_mut() {
+        transform.rotation = rotation;
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
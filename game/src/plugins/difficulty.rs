@@ -0,0 +1,157 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use super::{gravity::MARS_GRAVITY, mission::Mission};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn preset(self) -> DifficultyPreset {
+        match self {
+            Difficulty::Easy => DifficultyPreset {
+                fuel_multiplier: 1.5,
+                wind_multiplier: 0.5,
+                touchdown_speed_multiplier: 1.5,
+                tilt_multiplier: 1.5,
+                gravity: 3.0,
+                max_thrust: 8.0,
+                max_ecs: 3.0,
+                assists: Assists {
+                    sas: true,
+                    trajectory_prediction: true,
+                    auto_throttle: true,
+                },
+            },
+            Difficulty::Normal => DifficultyPreset {
+                fuel_multiplier: 1.0,
+                wind_multiplier: 1.0,
+                touchdown_speed_multiplier: 1.0,
+                tilt_multiplier: 1.0,
+                gravity: MARS_GRAVITY,
+                max_thrust: 6.5,
+                max_ecs: 3.0,
+                assists: Assists {
+                    sas: false,
+                    trajectory_prediction: true,
+                    auto_throttle: false,
+                },
+            },
+            Difficulty::Hard => DifficultyPreset {
+                fuel_multiplier: 0.75,
+                wind_multiplier: 1.5,
+                touchdown_speed_multiplier: 0.75,
+                tilt_multiplier: 0.75,
+                gravity: MARS_GRAVITY,
+                max_thrust: 6.0,
+                max_ecs: 2.5,
+                assists: Assists::default(),
+            },
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty: {}", s)),
+        }
+    }
+}
+
+/// Help the pilot can switch on or off, whatever the difficulty.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Assists {
+    /// Starts missions with the SAS steadying the rocket.
+    pub sas: bool,
+    /// Shows where the rocket would hit the ground if the engines cut.
+    pub trajectory_prediction: bool,
+    /// Engages the autopilot's descent rate hold at the start of a mission.
+    pub auto_throttle: bool,
+}
+
+/// Accepts `none`, `all` or a comma separated list such as `sas,prediction`.
+impl FromStr for Assists {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut assists = Assists::default();
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "none" => {}
+                "all" => {
+                    assists = Assists {
+                        sas: true,
+                        trajectory_prediction: true,
+                        auto_throttle: true,
+                    }
+                }
+                "sas" => assists.sas = true,
+                "prediction" => assists.trajectory_prediction = true,
+                "auto_throttle" => assists.auto_throttle = true,
+                _ => return Err(format!("unknown assist: {}", name)),
+            }
+        }
+        Ok(assists)
+    }
+}
+
+/// Tuning for a difficulty level. Mission files are written for Normal and
+/// scaled by the multipliers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyPreset {
+    pub fuel_multiplier: f32,
+    pub wind_multiplier: f32,
+    pub touchdown_speed_multiplier: f32,
+    pub tilt_multiplier: f32,
+    /// Surface gravity in m/s².
+    pub gravity: f32,
+    pub max_thrust: f32,
+    pub max_ecs: f32,
+    /// Assists switched on when this difficulty is picked.
+    pub assists: Assists,
+}
+
+impl Default for DifficultyPreset {
+    fn default() -> Self {
+        Difficulty::default().preset()
+    }
+}
+
+impl DifficultyPreset {
+    /// Copy of `mission` with its fuel and landing limits scaled.
+    pub fn apply(&self, mission: &Mission) -> Mission {
+        let mut mission = mission.clone();
+        mission.start.fuel *= self.fuel_multiplier;
+        mission.success.max_touchdown_speed *= self.touchdown_speed_multiplier;
+        mission.success.max_tilt_degrees *= self.tilt_multiplier;
+        mission
+    }
+}
+
+/// Preset the current mission was started with, kept until the next launch
+/// so changing difficulty mid-flight doesn't change the rocket.
+#[derive(Resource, Default)]
+pub struct ActiveDifficulty(pub DifficultyPreset);
@@ -0,0 +1,153 @@
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use rand::Rng;
+
+use super::{
+    camera::CameraRig,
+    settings::Settings,
+    splash::in_flight,
+    weather::{Dust, WindDirection, WindSpeed},
+};
+
+const CLEAR_FOG_DENSITY: f32 = 0.008;
+const STORM_FOG_DENSITY: f32 = 0.09;
+const DUST_COLOR: Srgba = Srgba::rgb(0.62, 0.42, 0.28);
+// motes in a full storm, never more than the particle budget
+const MAX_MOTES: usize = 800;
+// half the size of the box around the camera the motes fill
+const MOTE_EXTENT: Vec3 = Vec3::new(12.0, 8.0, 12.0);
+const MOTE_TURBULENCE: f32 = 0.6;
+// motes drift faster than the wind force alone would push the rocket
+const MOTE_WIND_SPEED: f32 = 2.0;
+
+/// A speck of airborne dust, kept around the camera.
+#[derive(Component)]
+struct DustMote {
+    drift: Vec3,
+}
+
+#[derive(Resource)]
+struct DustAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+pub struct DustPlugin;
+
+impl Plugin for DustPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (dust_fog_system, dust_mote_count_system))
+            .add_systems(Update, drift_dust_motes_system.run_if(in_flight));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(DustAssets {
+        mesh: meshes.add(Sphere::new(0.03).mesh().uv(3, 3)),
+        material: materials.add(StandardMaterial {
+            base_color: DUST_COLOR.with_alpha(0.6).into(),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+/// Thickens the fog and turns it the colour of the dust.
+fn dust_fog_system(
+    dust: Res<Dust>,
+    settings: Res<Settings>,
+    mut cameras: Query<&mut FogSettings, With<CameraRig>>,
+) {
+    if !dust.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let [red, green, blue] = settings.graphics.fog_color;
+    for mut fog in cameras.iter_mut() {
+        fog.color = Srgba::rgb(red, green, blue)
+            .mix(&DUST_COLOR, dust.opacity)
+            .into();
+        fog.falloff = FogFalloff::ExponentialSquared {
+            density: CLEAR_FOG_DENSITY + (STORM_FOG_DENSITY - CLEAR_FOG_DENSITY) * dust.opacity,
+        };
+    }
+}
+
+fn dust_mote_count_system(
+    mut commands: Commands,
+    dust: Res<Dust>,
+    settings: Res<Settings>,
+    assets: Option<Res<DustAssets>>,
+    cameras: Query<&Transform, With<CameraRig>>,
+    motes: Query<Entity, With<DustMote>>,
+) {
+    let (Some(assets), Ok(camera)) = (assets, cameras.get_single()) else {
+        return;
+    };
+
+    let wanted = (MAX_MOTES.min(settings.graphics.particle_budget) as f32 * dust.opacity) as usize;
+    let count = motes.iter().count();
+
+    for mote in motes.iter().skip(wanted) {
+        commands.entity(mote).despawn();
+    }
+
+    let mut rng = rand::thread_rng();
+    for _ in count..wanted {
+        let offset = Vec3::new(
+            rng.gen_range(-MOTE_EXTENT.x..MOTE_EXTENT.x),
+            rng.gen_range(-MOTE_EXTENT.y..MOTE_EXTENT.y),
+            rng.gen_range(-MOTE_EXTENT.z..MOTE_EXTENT.z),
+        );
+        let drift = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        ) * MOTE_TURBULENCE;
+
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                transform: Transform::from_translation(camera.translation + offset),
+                ..default()
+            },
+            NotShadowCaster,
+            NotShadowReceiver,
+            DustMote { drift },
+        ));
+    }
+}
+
+/// Blows the motes along with the wind, wrapping them around the camera so
+/// the box never empties.
+fn drift_dust_motes_system(
+    time: Res<Time>,
+    cameras: Query<&Transform, With<CameraRig>>,
+    wind_query: Query<(&WindDirection, &WindSpeed)>,
+    mut motes: Query<(&mut Transform, &DustMote), Without<CameraRig>>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    let wind: Vec3 = wind_query
+        .iter()
+        .map(|(direction, speed)| direction.value * speed.value * MOTE_WIND_SPEED)
+        .sum();
+
+    for (mut transform, mote) in motes.iter_mut() {
+        transform.translation += (wind + mote.drift) * time.delta_seconds();
+
+        let offset = transform.translation - camera.translation;
+        let wrapped = (offset + MOTE_EXTENT).rem_euclid(MOTE_EXTENT * 2.0) - MOTE_EXTENT;
+        transform.translation = camera.translation + wrapped;
+    }
+}
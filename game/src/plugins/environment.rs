@@ -1,18 +1,213 @@
+use std::{collections::HashMap, f32::consts::TAU, path::Path};
+
 use bevy::{
+    asset::io::file::FileAssetReader,
+    log,
     pbr::{CascadeShadowConfigBuilder, NotShadowCaster, NotShadowReceiver},
     prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+    transform::TransformSystem,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_derive::Deserialize;
+
+use super::{
+    mission::{ActiveMission, MissionStarted},
+    settings::Settings,
+    terrain::TerrainSource,
 };
 
-use rand::Rng;
+/// Sky and planet variants, relative to the asset folder.
+pub const ENVIRONMENT_MANIFEST: &str = "environment.json";
+
+// half the width of a magnitude 0 star, at the starfield radius
+const STAR_SIZE: f32 = 0.1;
+const FAINTEST_MAGNITUDE: f32 = 6.5;
+
 pub struct EnvironmentPlugin;
 
+/// The directional light standing in for the sun.
+#[derive(Component)]
+pub struct Sun;
+
+/// Surface of the sky, lit by the daylight system from black to `tint`.
+#[derive(Component)]
+pub struct Sky {
+    pub tint: Srgba,
+}
+
+/// Root of the sky currently shown.
+#[derive(Component)]
+struct SkyDome {
+    tint: Srgba,
+}
+
+/// The merged mesh holding every star.
+#[derive(Component)]
+pub struct Star;
+
+/// Where stars come from. The same seed gives the same sky every launch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StarfieldSpec {
+    pub seed: u64,
+    /// Bright stars placed first, from lines of right ascension and
+    /// declination in degrees and visual magnitude.
+    pub catalogue: Option<String>,
+    /// Distance from the camera, inside the sky.
+    pub radius: f32,
+}
+
+impl Default for StarfieldSpec {
+    fn default() -> Self {
+        Self {
+            seed: 4242,
+            catalogue: None,
+            radius: 25.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StarPoint {
+    direction: Vec3,
+    magnitude: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SkySpec {
+    /// A glTF scene drawn as the sky, with its own textures.
+    pub model: Option<String>,
+    /// Image wrapped around a sphere when there is no model.
+    pub texture: Option<String>,
+    /// Uniform scale. The generated sphere has a radius of 1.
+    pub scale: f32,
+    /// Colour the sky is multiplied by in full daylight.
+    pub tint: [f32; 3],
+}
+
+impl Default for SkySpec {
+    fn default() -> Self {
+        Self {
+            model: None,
+            texture: None,
+            scale: 30.0,
+            tint: [0.42, 0.28, 0.2],
+        }
+    }
+}
+
+/// Skies and planets missions pick from by name.
+#[derive(Resource, Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentManifest {
+    pub default_sky: String,
+    pub skies: HashMap<String, SkySpec>,
+    pub planets: HashMap<String, TerrainSource>,
+    pub stars: StarfieldSpec,
+}
+
+impl Default for EnvironmentManifest {
+    fn default() -> Self {
+        Self {
+            default_sky: "plain".to_string(),
+            skies: HashMap::from([("plain".to_string(), SkySpec::default())]),
+            planets: HashMap::new(),
+            stars: StarfieldSpec::default(),
+        }
+    }
+}
+
+impl EnvironmentManifest {
+    pub fn load(path: &Path) -> Self {
+        let manifest = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                serde_json::from_str::<EnvironmentManifest>(&contents).map_err(|e| e.to_string())
+            });
+        match manifest {
+            Ok(mut manifest) => {
+                // without a collision mesh the rocket would fall through the
+                // visible ground onto the fallback sphere
+                manifest.planets.retain(|name, planet| {
+                    let missing = matches!(planet, TerrainSource::Asset { collider: None, .. });
+                    if missing {
+                        log::warn!("Planet {} has no collider, leaving it out", name);
+                    }
+                    !missing
+                });
+                manifest
+            }
+            Err(e) => {
+                log::warn!("Failed to read environment manifest {:?}: {}", path, e);
+                EnvironmentManifest::default()
+            }
+        }
+    }
+
+    /// Named sky, or the default one for missions that don't pick or pick
+    /// one that isn't listed.
+    fn sky(&self, name: Option<&str>) -> (String, SkySpec) {
+        if let Some(name) = name {
+            match self.skies.get(name) {
+                Some(sky) => return (name.to_string(), sky.clone()),
+                None => log::warn!("Unknown sky {}, using the default", name),
+            }
+        }
+        let sky = self
+            .skies
+            .get(&self.default_sky)
+            .cloned()
+            .unwrap_or_default();
+        (self.default_sky.clone(), sky)
+    }
+}
+
+/// Manifest entries a mission uses in place of its own terrain and the
+/// default sky.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MissionEnvironment {
+    pub sky: Option<String>,
+    pub planet: Option<String>,
+}
+
+/// Material shared by every star, so they can be faded together, and what
+/// the starfield is rebuilt from when the star count changes.
+#[derive(Resource)]
+pub struct StarAssets {
+    pub material: Handle<StandardMaterial>,
+    spec: StarfieldSpec,
+    catalogue: Vec<StarPoint>,
+    count: usize,
+}
+
 impl Plugin for EnvironmentPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup);
+        app.add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    apply_mission_sky_system,
+                    sky_scene_ready_system,
+                    star_count_system.run_if(resource_changed::<Settings>),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_camera_system.before(TransformSystem::TransformPropagate),
+            );
     }
 }
 fn setup(
     mut commands: Commands,
+    settings: Res<Settings>,
+    manifest: Res<EnvironmentManifest>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
@@ -24,63 +219,346 @@ fn setup(
     .build();
 
     // // Sun
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            color: Srgba::rgb(0.98, 0.95, 0.82).into(),
-            illuminance: 3000.0,
-            shadows_enabled: true,
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Srgba::rgb(0.98, 0.95, 0.82).into(),
+                illuminance: 3000.0,
+                shadows_enabled: settings.graphics.quality.shadows(),
+                ..default()
+            },
+            transform: Transform::from_xyz(30.0, 40.0, 5.0)
+                .looking_at(Vec3::new(-0.15, -0.05, 0.25), Vec3::Y),
+            cascade_shadow_config,
             ..default()
         },
-        transform: Transform::from_xyz(30.0, 40.0, 5.0)
-            .looking_at(Vec3::new(-0.15, -0.05, 0.25), Vec3::Y),
-        cascade_shadow_config,
-        ..default()
+        Sun,
+    ));
+
+    let spec = manifest.stars.clone();
+    let catalogue = spec.catalogue.as_ref().map_or_else(Vec::new, |catalogue| {
+        load_star_catalogue(
+            &FileAssetReader::get_base_path()
+                .join("assets")
+                .join(catalogue),
+        )
     });
+    let star_assets = StarAssets {
+        material: materials.add(StandardMaterial {
+            unlit: true,
+            cull_mode: None,
+            base_color: Srgba::rgb(1.0, 1.0, 1.0).into(),
+            emissive: Srgba::rgb(1.0, 1.0, 1.0).into(),
+            ..default()
+        }),
+        spec,
+        catalogue,
+        count: settings.graphics.star_count,
+    };
 
-    // Sky
     commands.spawn((
         PbrBundle {
-            mesh: meshes.add(Sphere::new(30.0)),
-            material: materials.add(StandardMaterial {
-                cull_mode: None,
-                unlit: true,
-                ior: 1.0,
-                perceptual_roughness: 1000.0,
-                base_color: Srgba::rgb(0.0, 0.0, 0.0).into(),
-                ..default()
-            }),
-            transform: Transform::from_scale(Vec3::splat(1.0)),
+            mesh: meshes.add(starfield_mesh(&star_assets)),
+            material: star_assets.material.clone(),
             ..default()
         },
         NotShadowCaster,
         NotShadowReceiver,
+        Star,
     ));
+    commands.insert_resource(star_assets);
+}
 
-    // random stars
-    for _ in 0..100 {
-        let x = rand::thread_rng().gen_range(-25.0..25.0);
-        let y = rand::thread_rng().gen_range(2.0..37.0);
-        let z = rand::thread_rng().gen_range(-15.0..-5.0);
-        let scale = rand::random::<f32>() * 0.05;
+fn apply_mission_sky_system(
+    mut commands: Commands,
+    mut mission_started: EventReader<MissionStarted>,
+    mut current: Local<Option<String>>,
+    mission: Res<ActiveMission>,
+    manifest: Res<EnvironmentManifest>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    domes: Query<Entity, With<SkyDome>>,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+
+    let (name, spec) = manifest.sky(mission.0.environment.sky.as_deref());
+    if current.as_ref() == Some(&name) {
+        return;
+    }
+    for dome in domes.iter() {
+        commands.entity(dome).despawn_recursive();
+    }
 
+    let [red, green, blue] = spec.tint;
+    let tint = Srgba::rgb(red, green, blue);
+    let transform = Transform::from_scale(Vec3::splat(spec.scale));
+
+    if let Some(model) = &spec.model {
+        log::info!("Loading sky {}", model);
+        commands.spawn((
+            SceneBundle {
+                scene: asset_server.load(format!("{}#Scene0", model)),
+                transform,
+                ..default()
+            },
+            SkyDome { tint },
+        ));
+    } else {
+        // a texture is drawn as is, the plain sphere fades into the fog
+        let texture = spec.texture.as_ref().map(|path| asset_server.load(path));
         commands.spawn((
             PbrBundle {
-                mesh: meshes.add(Sphere::default().mesh().uv(3, 3)),
+                mesh: meshes.add(Sphere::new(1.0).mesh().uv(64, 32)),
                 material: materials.add(StandardMaterial {
+                    cull_mode: None,
                     unlit: true,
-                    base_color: Srgba::rgb(1.0, 1.0, 1.0).into(),
-                    emissive: Srgba::rgb(1.0, 1.0, 1.0).into(),
+                    fog: texture.is_none(),
+                    base_color: Color::BLACK,
+                    base_color_texture: texture,
                     ..default()
                 }),
-                transform: Transform {
-                    translation: Vec3::new(x, y, z),
-                    rotation: Quat::from_rotation_y(rand::random::<f32>() * std::f32::consts::PI),
-                    scale: Vec3::splat(scale),
-                },
+                transform,
                 ..default()
             },
             NotShadowCaster,
             NotShadowReceiver,
+            Sky { tint },
+            SkyDome { tint },
         ));
     }
+    *current = Some(name);
+}
+
+/// Turns the meshes of a loaded sky model into unlit sky surfaces.
+fn sky_scene_ready_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    added_materials: Query<(Entity, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    parents: Query<&Parent>,
+    domes: Query<&SkyDome>,
+) {
+    for (entity, handle) in added_materials.iter() {
+        let Some(dome) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| domes.get(ancestor).ok())
+        else {
+            continue;
+        };
+
+        if let Some(material) = materials.get_mut(handle) {
+            material.unlit = true;
+            material.fog = false;
+            material.cull_mode = None;
+        }
+        commands.entity(entity).insert((
+            NotShadowCaster,
+            NotShadowReceiver,
+            Sky { tint: dome.tint },
+        ));
+    }
+}
+
+/// Rebuilds the starfield when the count is changed on the settings screen.
+fn star_count_system(
+    settings: Res<Settings>,
+    mut star_assets: ResMut<StarAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stars: Query<&mut Handle<Mesh>, With<Star>>,
+) {
+    if star_assets.count == settings.graphics.star_count {
+        return;
+    }
+
+    star_assets.count = settings.graphics.star_count;
+    for mut mesh in stars.iter_mut() {
+        *mesh = meshes.add(starfield_mesh(&star_assets));
+    }
+}
+
+/// Keeps the sky and stars centred on the camera, so they always surround it.
+fn follow_camera_system(
+    camera: Query<&Transform, With<Camera3d>>,
+    mut backdrop: Query<&mut Transform, (Or<(With<SkyDome>, With<Star>)>, Without<Camera3d>)>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for mut transform in backdrop.iter_mut() {
+        transform.translation = camera.translation;
+    }
+}
+
+fn load_star_catalogue(path: &Path) -> Vec<StarPoint> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::warn!("Failed to read star catalogue {:?}: {:?}", path, e);
+            return Vec::new();
+        }
+    };
+    parse_star_catalogue(&contents)
+}
+
+fn parse_star_catalogue(contents: &str) -> Vec<StarPoint> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<f32> = line
+                .split(',')
+                .map(|field| field.trim().parse())
+                .collect::<Result<_, _>>()
+                .ok()?;
+            let [right_ascension, declination, magnitude] = fields[..] else {
+                return None;
+            };
+            let (right_ascension, declination) =
+                (right_ascension.to_radians(), declination.to_radians());
+            Some(StarPoint {
+                direction: Vec3::new(
+                    declination.cos() * right_ascension.cos(),
+                    declination.sin(),
+                    -declination.cos() * right_ascension.sin(),
+                ),
+                magnitude,
+            })
+        })
+        .collect()
+}
+
+/// Catalogue stars first, then seeded random ones spread evenly over the
+/// sphere, mostly faint as in the real sky.
+fn generate_stars(star_assets: &StarAssets) -> Vec<StarPoint> {
+    let mut rng = StdRng::seed_from_u64(star_assets.spec.seed);
+    let mut stars: Vec<StarPoint> = star_assets
+        .catalogue
+        .iter()
+        .take(star_assets.count)
+        .copied()
+        .collect();
+
+    while stars.len() < star_assets.count {
+        let height: f32 = rng.gen_range(-1.0..1.0);
+        let angle = rng.gen_range(0.0..TAU);
+        let across = (1.0 - height * height).sqrt();
+        stars.push(StarPoint {
+            direction: Vec3::new(across * angle.cos(), height, across * angle.sin()),
+            magnitude: FAINTEST_MAGNITUDE - 5.0 * rng.gen::<f32>().powi(3),
+        });
+    }
+    stars
+}
+
+/// One mesh for the whole sky: a small octahedron per star, shaded by
+/// brightness through vertex colours.
+fn starfield_mesh(star_assets: &StarAssets) -> Mesh {
+    const CORNERS: [Vec3; 6] = [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ];
+    const FACES: [u32; 24] = [
+        0, 2, 4, 2, 1, 4, 1, 3, 4, 3, 0, 4, 2, 0, 5, 1, 2, 5, 3, 1, 5, 0, 3, 5,
+    ];
+
+    let stars = generate_stars(star_assets);
+    let mut positions = Vec::with_capacity(stars.len() * CORNERS.len());
+    let mut normals = Vec::with_capacity(stars.len() * CORNERS.len());
+    let mut colors = Vec::with_capacity(stars.len() * CORNERS.len());
+    let mut indices = Vec::with_capacity(stars.len() * FACES.len());
+
+    for star in stars.iter() {
+        let brightness = FAINTEST_MAGNITUDE - star.magnitude;
+        let size = STAR_SIZE * (0.3 + 0.15 * brightness);
+        let shade = (brightness / 5.0).clamp(0.25, 1.0);
+        let center = star.direction * star_assets.spec.radius;
+
+        let first = positions.len() as u32;
+        for corner in CORNERS {
+            positions.push((center + corner * size).to_array());
+            normals.push(corner.to_array());
+            colors.push([shade, shade, shade, 1.0]);
+        }
+        indices.extend(FACES.iter().map(|index| first + index));
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star_assets(seed: u64, catalogue: Vec<StarPoint>, count: usize) -> StarAssets {
+        StarAssets {
+            material: Handle::default(),
+            spec: StarfieldSpec { seed, ..default() },
+            catalogue,
+            count,
+        }
+    }
+
+    #[test]
+    fn catalogue_lines_are_parsed() {
+        let stars = parse_star_catalogue(
+            "# ra, dec, magnitude\n\
+             0, 0, 1.5\n\
+             90, 0, 2\n\
+             \n\
+             12, 90, -0.5\n\
+             not, a, star\n\
+             1, 2\n",
+        );
+
+        assert_eq!(stars.len(), 3);
+        assert!(stars[0].direction.abs_diff_eq(Vec3::X, 1e-5));
+        assert!(stars[1].direction.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+        assert!(stars[2].direction.abs_diff_eq(Vec3::Y, 1e-5));
+        assert_eq!(stars[2].magnitude, -0.5);
+    }
+
+    #[test]
+    fn same_seed_gives_same_stars() {
+        let first = generate_stars(&star_assets(7, Vec::new(), 200));
+        let second = generate_stars(&star_assets(7, Vec::new(), 200));
+        let other = generate_stars(&star_assets(8, Vec::new(), 200));
+
+        assert_eq!(first.len(), 200);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert_eq!(a.direction, b.direction);
+            assert_eq!(a.magnitude, b.magnitude);
+        }
+        assert!(first
+            .iter()
+            .zip(other.iter())
+            .any(|(a, b)| a.direction != b.direction));
+    }
+
+    #[test]
+    fn catalogue_stars_come_first() {
+        let catalogue = parse_star_catalogue("45, 30, 0.5\n200, -60, 1\n");
+        let stars = generate_stars(&star_assets(7, catalogue.clone(), 50));
+
+        assert_eq!(stars.len(), 50);
+        assert_eq!(stars[0].direction, catalogue[0].direction);
+        assert_eq!(stars[1].direction, catalogue[1].direction);
+        for star in stars.iter() {
+            assert!((star.direction.length() - 1.0).abs() < 1e-4);
+            assert!(star.magnitude <= FAINTEST_MAGNITUDE);
+        }
+    }
 }
@@ -0,0 +1,338 @@
+use bevy::{pbr::NotShadowCaster, prelude::*};
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use super::{
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    rocket::{Rocket, Thrust},
+    splash::in_flight,
+    weather::{WindDirection, WindSpeed},
+};
+
+// particles emitted per second at full thrust
+const EMISSION_RATE: f32 = 480.0;
+const PARTICLE_LIFETIME: f32 = 2.5;
+// distance from the rocket's origin to the nozzle along its local down axis
+const NOZZLE_OFFSET: f32 = 0.2;
+// how strongly the wind pushes the plume around
+const WIND_DRAG: f32 = 0.35;
+const GROUND_FRICTION: f32 = 1.2;
+// how far above and below a grounded particle the surface is looked for
+const GROUND_PROBE: f32 = 0.3;
+const DUST_CHANCE: f64 = 0.4;
+
+/// Maximum number of exhaust particles alive at once.
+#[derive(Resource, Clone, Copy)]
+pub struct ExhaustSettings {
+    pub budget: usize,
+}
+
+impl Default for ExhaustSettings {
+    fn default() -> Self {
+        Self { budget: 600 }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ParticleStats {
+    pub spawned: u64,
+}
+
+/// Particle entities are created once and recycled, hidden while unused.
+#[derive(Resource, Default)]
+pub struct ParticlePool {
+    free: Vec<Entity>,
+    total: usize,
+}
+
+impl ParticlePool {
+    pub fn active(&self) -> usize {
+        self.total - self.free.len()
+    }
+}
+
+#[derive(Resource)]
+struct ExhaustAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    dust_material: Handle<StandardMaterial>,
+}
+
+#[derive(Component, Default)]
+pub struct Particle {
+    velocity: Vec3,
+    lifetime: f32,
+    active: bool,
+    grounded: bool,
+}
+
+impl Particle {
+    fn launch(&mut self, velocity: Vec3, lifetime: f32) {
+        self.velocity = velocity;
+        self.lifetime = lifetime;
+        self.active = true;
+        self.grounded = false;
+    }
+}
+
+pub struct ExhaustPlugin;
+
+impl Plugin for ExhaustPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExhaustSettings>()
+            .init_resource::<ParticleStats>()
+            .init_resource::<ParticlePool>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    // new pool entities must exist before they are handed out
+                    resize_pool_system,
+                    apply_deferred,
+                    (particle_emitter_system, update_particle_system)
+                        .chain()
+                        .run_if(in_flight),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ExhaustAssets {
+        mesh: meshes.add(Sphere::default().mesh().uv(3, 3)),
+        material: materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 1.0, 1.0, 0.5),
+            cull_mode: None,
+            ..default()
+        }),
+        dust_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.62, 0.38, 0.24),
+            perceptual_roughness: 1.0,
+            cull_mode: None,
+            ..default()
+        }),
+    });
+}
+
+fn resize_pool_system(
+    mut commands: Commands,
+    settings: Res<ExhaustSettings>,
+    assets: Option<Res<ExhaustAssets>>,
+    mut pool: ResMut<ParticlePool>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+
+    while pool.total < settings.budget {
+        let entity = commands
+            .spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: assets.material.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                NotShadowCaster,
+                Particle::default(),
+            ))
+            .id();
+        pool.free.push(entity);
+        pool.total += 1;
+    }
+
+    // particles still in flight are released once they expire
+    while pool.total > settings.budget {
+        let Some(entity) = pool.free.pop() else {
+            break;
+        };
+        commands.entity(entity).despawn();
+        pool.total -= 1;
+    }
+}
+
+fn particle_emitter_system(
+    time: Res<Time>,
+    difficulty: Res<ActiveDifficulty>,
+    assets: Option<Res<ExhaustAssets>>,
+    mut emission: Local<f32>,
+    mut pool: ResMut<ParticlePool>,
+    mut particle_stats: ResMut<ParticleStats>,
+    rocket_query: Query<(&Transform, &Thrust), (With<Rocket>, Without<Particle>)>,
+    mut particles: Query<(
+        &mut Particle,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+    let Ok((rocket_transform, thrust)) = rocket_query.get_single() else {
+        return;
+    };
+
+    let thrust = thrust.value;
+    if thrust <= 0.0 {
+        *emission = 0.0;
+        return;
+    }
+
+    *emission += EMISSION_RATE * (thrust / difficulty.0.max_thrust) * time.delta_seconds();
+    let mut rng = rand::thread_rng();
+    let rotation = rocket_transform.rotation;
+    let nozzle = rocket_transform.translation + rotation * Vec3::new(0.0, -NOZZLE_OFFSET, 0.0);
+
+    while *emission >= 1.0 {
+        *emission -= 1.0;
+
+        let Some(entity) = pool.free.pop() else {
+            // over budget, skip this frame's remainder
+            *emission = 0.0;
+            break;
+        };
+        let Ok((mut particle, mut transform, mut visibility, mut material)) =
+            particles.get_mut(entity)
+        else {
+            pool.free.push(entity);
+            break;
+        };
+
+        // exhaust leaves the nozzle along the rocket's local down axis
+        let local_velocity = Vec3::new(
+            rng.gen_range(-0.45..0.45),
+            rng.gen_range(2.5..((thrust / 5.0) + 2.5)) * -1.0,
+            rng.gen_range(-0.45..0.45),
+        );
+        particle.launch(rotation * local_velocity, PARTICLE_LIFETIME);
+
+        *transform = Transform {
+            translation: nozzle,
+            // random rotation
+            rotation: Quat::from_rotation_y(rng.gen_range(0.0..std::f32::consts::PI)),
+            scale: Vec3::splat(rng.gen_range(0.01..0.1)),
+        };
+        *visibility = Visibility::Visible;
+        *material = assets.material.clone();
+        particle_stats.spawned += 1;
+    }
+}
+
+fn update_particle_system(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    gravity: Res<PlanetGravity>,
+    assets: Option<Res<ExhaustAssets>>,
+    mut pool: ResMut<ParticlePool>,
+    mut particle_stats: ResMut<ParticleStats>,
+    wind_query: Query<(&WindDirection, &WindSpeed)>,
+    mut particles: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+
+    let delta = time.delta_seconds();
+    let wind = wind_query
+        .iter()
+        .map(|(direction, speed)| direction.value * speed.value)
+        .sum::<Vec3>();
+    let mut rng = rand::thread_rng();
+    let mut dust = Vec::new();
+
+    for (entity, mut particle, mut transform, mut visibility, _) in particles.iter_mut() {
+        if !particle.active {
+            continue;
+        }
+
+        let mut velocity = particle.velocity + wind * WIND_DRAG * delta;
+        if !particle.grounded {
+            velocity += gravity.acceleration_at(transform.translation) * delta;
+        }
+        let displacement = velocity * delta;
+
+        if particle.grounded {
+            velocity *= (1.0 - GROUND_FRICTION * delta).max(0.0);
+            // follow the surface, and fall again off an edge
+            let up = gravity.up_at(transform.translation);
+            let moved = transform.translation + displacement;
+            if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+                moved + up * GROUND_PROBE,
+                -up,
+                2.0 * GROUND_PROBE,
+                true,
+                QueryFilter::only_fixed(),
+            ) {
+                velocity -= hit.normal * velocity.dot(hit.normal);
+                transform.translation = hit.point + hit.normal * 0.02;
+            } else {
+                transform.translation = moved;
+                particle.grounded = false;
+            }
+        } else if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+            transform.translation,
+            displacement,
+            1.0,
+            true,
+            QueryFilter::only_fixed(),
+        ) {
+            // the plume flattens out along the surface and spreads radially
+            let normal = hit.normal;
+            let tangential = velocity - normal * velocity.dot(normal);
+            let spread = Vec3::new(rng.gen_range(-0.5..0.5), 0.0, rng.gen_range(-0.5..0.5));
+            velocity = (tangential + spread - normal * spread.dot(normal)) * 0.8;
+            transform.translation = hit.point + normal * 0.02;
+            particle.grounded = true;
+
+            if rng.gen_bool(DUST_CHANCE) {
+                let kick = tangential.normalize_or_zero() + normal * rng.gen_range(0.4..1.2);
+                dust.push((hit.point + normal * 0.05, kick * rng.gen_range(0.5..1.5)));
+            }
+        } else {
+            transform.translation += displacement;
+        }
+
+        particle.velocity = velocity;
+        transform.scale += Vec3::splat(delta * 0.1);
+        particle.lifetime -= delta;
+
+        if particle.lifetime <= 0.0 {
+            particle.active = false;
+            *visibility = Visibility::Hidden;
+            pool.free.push(entity);
+        }
+    }
+
+    // dust kicked up by the plume comes out of the same pool
+    for (position, velocity) in dust {
+        let Some(entity) = pool.free.pop() else {
+            break;
+        };
+        let Ok((_, mut particle, mut transform, mut visibility, mut material)) =
+            particles.get_mut(entity)
+        else {
+            pool.free.push(entity);
+            break;
+        };
+
+        particle.launch(velocity, PARTICLE_LIFETIME * 0.6);
+        *transform = Transform::from_translation(position)
+            .with_scale(Vec3::splat(rng.gen_range(0.02..0.06)));
+        *visibility = Visibility::Visible;
+        *material = assets.dust_material.clone();
+        particle_stats.spawned += 1;
+    }
+}
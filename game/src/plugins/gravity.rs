@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::splash::in_flight;
+
+pub const MARS_GRAVITY: f32 = 3.71;
+
+/// Gravity pulling toward the centre of a spherical planet.
+///
+/// The planet is tiny compared to the real one, so the pull keeps the same
+/// strength at every altitude and only its direction changes.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PlanetGravity {
+    pub center: Vec3,
+    pub radius: f32,
+    pub surface_gravity: f32,
+}
+
+impl Default for PlanetGravity {
+    fn default() -> Self {
+        Self {
+            center: Vec3::new(0.0, -25.0, 0.0),
+            radius: 25.0,
+            surface_gravity: MARS_GRAVITY,
+        }
+    }
+}
+
+impl PlanetGravity {
+    /// Local "up", pointing away from the planet centre.
+    pub fn up_at(&self, position: Vec3) -> Vec3 {
+        (position - self.center).try_normalize().unwrap_or(Vec3::Y)
+    }
+
+    /// Height above the idealised planet surface.
+    pub fn altitude_at(&self, position: Vec3) -> f32 {
+        position.distance(self.center) - self.radius
+    }
+
+    pub fn acceleration_at(&self, position: Vec3) -> Vec3 {
+        -self.up_at(position) * self.surface_gravity
+    }
+
+    pub fn force_at(&self, position: Vec3, mass: f32) -> Vec3 {
+        self.acceleration_at(position) * mass
+    }
+}
+
+/// The share of a body's `ExternalForce` that is the planet's pull, so it
+/// can be taken off again before the body's own forces are set.
+#[derive(Component, Default)]
+struct PlanetPull {
+    force: Vec3,
+}
+
+pub struct GravityPlugin;
+
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        // Rapier's gravity is a single global vector, so it is switched off
+        // and bodies get their pull from `PlanetGravity` instead
+        app.init_resource::<PlanetGravity>()
+            .insert_resource(RapierConfiguration {
+                gravity: Vec3::ZERO,
+                ..RapierConfiguration::new(1.0)
+            })
+            .add_systems(PreUpdate, remove_planet_pull_system)
+            .add_systems(Update, track_dynamic_bodies_system)
+            .add_systems(
+                PostUpdate,
+                apply_planet_pull_system
+                    .run_if(in_flight)
+                    .before(PhysicsSet::SyncBackend),
+            );
+    }
+}
+
+/// Has Rapier report the mass of every new dynamic body, and gives it a
+/// force to carry the pull.
+fn track_dynamic_bodies_system(
+    mut commands: Commands,
+    bodies: Query<
+        (Entity, &RigidBody, Has<ExternalForce>),
+        (Added<RigidBody>, Without<PlanetPull>),
+    >,
+) {
+    for (entity, body, has_force) in bodies.iter() {
+        if *body != RigidBody::Dynamic {
+            continue;
+        }
+        let mut entity = commands.entity(entity);
+        entity.insert((ReadMassProperties::default(), PlanetPull::default()));
+        if !has_force {
+            entity.insert(ExternalForce::default());
+        }
+    }
+}
+
+/// Takes last frame's pull off, leaving only the forces bodies set
+/// themselves.
+fn remove_planet_pull_system(mut bodies: Query<(&mut ExternalForce, &mut PlanetPull)>) {
+    for (mut external, mut pull) in bodies.iter_mut() {
+        external.force -= pull.force;
+        pull.force = Vec3::ZERO;
+    }
+}
+
+/// Adds the pull once every system has set its forces, so Rapier integrates
+/// it over the same step as thrust and wind.
+fn apply_planet_pull_system(
+    gravity: Res<PlanetGravity>,
+    mut bodies: Query<(
+        &RigidBody,
+        &Transform,
+        &ReadMassProperties,
+        &mut ExternalForce,
+        &mut PlanetPull,
+    )>,
+) {
+    for (body, transform, mass, mut external, mut pull) in bodies.iter_mut() {
+        if *body == RigidBody::Dynamic {
+            pull.force = gravity.force_at(transform.translation, mass.get().mass);
+            external.force += pull.force;
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Mutex;
+
+use bevy::{log, prelude::*};
+use serde_derive::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+pub const GROUND_CONTROL_ADDRESS: &str = "127.0.0.1:8089";
+const MAX_ECS_BURN: f32 = 5.0;
+pub const MAX_TELEMETRY_RATE: f32 = 120.0;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EcsSide {
+    Left,
+    Right,
+}
+
+/// Commands a ground station can send, one JSON object per line, e.g.
+/// `{"command":"set_throttle","value":0.5}`.
+#[derive(Event, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum GroundCommand {
+    SetThrottle { value: f32 },
+    FireEcs { side: EcsSide, duration: f32 },
+    ReleaseControls,
+    Pause,
+    Resume,
+    Reset,
+    SetWind { speed_kph: f32, degrees: f32 },
+    SetDust { opacity: f32 },
+    SetAutopilot { enabled: bool },
+    SetTelemetryRate { hz: f32 },
+}
+
+impl GroundCommand {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            GroundCommand::SetThrottle { value } if !(0.0..=1.0).contains(value) => Err(format!(
+                "throttle must be between 0.0 and 1.0, got {}",
+                value
+            )),
+            GroundCommand::FireEcs { duration, .. } if !(0.0..=MAX_ECS_BURN).contains(duration) => {
+                Err(format!(
+                    "ECS burn must be between 0.0 and {} seconds, got {}",
+                    MAX_ECS_BURN, duration
+                ))
+            }
+            GroundCommand::SetWind { speed_kph, degrees }
+                if !speed_kph.is_finite() || *speed_kph < 0.0 || !degrees.is_finite() =>
+            {
+                Err(format!(
+                    "invalid wind: {} kph at {} degrees",
+                    speed_kph, degrees
+                ))
+            }
+            // 0 for clear air to 1 for a full storm
+            GroundCommand::SetDust { opacity } if !(0.0..=1.0).contains(opacity) => Err(format!(
+                "dust opacity must be between 0.0 and 1.0, got {}",
+                opacity
+            )),
+            GroundCommand::SetTelemetryRate { hz } if !(*hz > 0.0 && *hz <= MAX_TELEMETRY_RATE) => {
+                Err(format!(
+                    "telemetry rate must be above 0 and at most {} Hz, got {}",
+                    MAX_TELEMETRY_RATE, hz
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct GroundControlChannel {
+    rx: Mutex<mpsc::Receiver<GroundCommand>>,
+}
+
+impl GroundControlChannel {
+    pub fn new(address: &str) -> Self {
+        let (tx, rx) = mpsc::channel::<GroundCommand>(32);
+        tokio::spawn(listen(address.to_string(), tx));
+        Self { rx: Mutex::new(rx) }
+    }
+}
+
+impl Default for GroundControlChannel {
+    fn default() -> Self {
+        Self::new(GROUND_CONTROL_ADDRESS)
+    }
+}
+
+async fn listen(address: String, tx: mpsc::Sender<GroundCommand>) {
+    let listener = match TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to bind ground control on {}: {:?}", address, e);
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                log::info!("Ground station connected from {}", addr);
+                tokio::spawn(handle_station(socket, tx.clone()));
+            }
+            Err(e) => {
+                log::error!("Failed to accept ground station: {:?}", e);
+            }
+        }
+    }
+}
+
+async fn handle_station(socket: TcpStream, tx: mpsc::Sender<GroundCommand>) {
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // every command gets a single line reply so scripted harnesses can
+        // wait for an acknowledgement before sending the next one
+        let reply = match serde_json::from_str::<GroundCommand>(&line) {
+            Ok(command) => match command.validate() {
+                Ok(()) => {
+                    if tx.send(command).await.is_err() {
+                        break;
+                    }
+                    "ok".to_string()
+                }
+                Err(reason) => format!("error: {}", reason),
+            },
+            Err(e) => format!("error: {}", e),
+        };
+
+        if writer
+            .write_all(format!("{}\n", reply).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+pub struct GroundControlPlugin {
+    pub address: String,
+}
+
+impl Default for GroundControlPlugin {
+    fn default() -> Self {
+        Self {
+            address: GROUND_CONTROL_ADDRESS.to_string(),
+        }
+    }
+}
+
+impl Plugin for GroundControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GroundControlChannel::new(&self.address))
+            .add_event::<GroundCommand>()
+            .add_systems(Update, receive_ground_commands_system);
+    }
+}
+
+fn receive_ground_commands_system(
+    channel: Res<GroundControlChannel>,
+    mut ground_commands: EventWriter<GroundCommand>,
+) {
+    let Ok(mut rx) = channel.rx.lock() else {
+        return;
+    };
+
+    while let Ok(command) = rx.try_recv() {
+        log::info!("Ground command received: {:?}", command);
+        ground_commands.send(command);
+    }
+}
@@ -0,0 +1,447 @@
+use bevy::prelude::*;
+use bevy_rapier3d::dynamics::Velocity as BodyVelocity;
+
+use super::{
+    altimeter::RadarAltimeter,
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    mission::ActiveMission,
+    rocket::{Fuel, Rocket, RocketCollider, Thrust},
+    sas::{Sas, SasMode},
+    settings::Settings,
+    splash::GameState,
+    trajectory::PredictedTrajectory,
+    weather::{WindDirection, WindSpeed},
+};
+
+const PANEL_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.45);
+const INSTRUMENT_COLOR: Color = Color::srgb(0.3, 1.0, 0.4);
+const WARNING_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const INACTIVE_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+const LOW_FUEL_FRACTION: f32 = 0.15;
+const MAX_SAFE_SLOPE: f32 = 15.0;
+// slope warnings only matter once the ground is close
+const SLOPE_WARNING_HEIGHT: f32 = 5.0;
+
+/// Whether the heads-up display is shown, toggled with H by default.
+#[derive(Resource)]
+pub struct HudSettings {
+    pub visible: bool,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self { visible: true }
+    }
+}
+
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct FuelGauge;
+
+#[derive(Component)]
+struct ThrottleBar;
+
+#[derive(Component)]
+struct FlightReadout;
+
+#[derive(Component)]
+struct AttitudeHorizon;
+
+#[derive(Component)]
+struct WindArrow;
+
+#[derive(Component)]
+struct WindReadout;
+
+#[derive(Component)]
+struct WarningReadout;
+
+#[derive(Component)]
+struct SasIndicator;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HudSettings>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    toggle_hud_system,
+                    gauges_system,
+                    flight_readout_system,
+                    wind_indicator_system,
+                    sas_indicator_system,
+                ),
+            );
+    }
+}
+
+fn text_style(font_size: f32) -> TextStyle {
+    TextStyle {
+        font_size,
+        color: INSTRUMENT_COLOR,
+        ..default()
+    }
+}
+
+fn panel() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(8.),
+            padding: UiRect::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: PANEL_BACKGROUND.into(),
+        ..default()
+    }
+}
+
+/// Vertical bar that fills from the bottom, with a label underneath.
+fn spawn_bar(parent: &mut ChildBuilder, label: &str, marker: impl Component) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|bar| {
+            bar.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Vh(2.5),
+                    height: Val::Vh(22.),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    border: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                border_color: INSTRUMENT_COLOR.into(),
+                ..default()
+            })
+            .with_children(|frame| {
+                frame.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        background_color: INSTRUMENT_COLOR.into(),
+                        ..default()
+                    },
+                    marker,
+                ));
+            });
+            bar.spawn(TextBundle::from_section(label, text_style(16.)));
+        });
+}
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::FlexEnd,
+                    padding: UiRect::all(Val::Vh(2.)),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            HudRoot,
+        ))
+        .with_children(|root| {
+            // fuel and throttle on the left
+            root.spawn(panel()).with_children(|left| {
+                left.spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(14.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|bars| {
+                    spawn_bar(bars, "FUEL", FuelGauge);
+                    spawn_bar(bars, "THR", ThrottleBar);
+                });
+                left.spawn((
+                    TextBundle::from_section(SasMode::Off.label(), text_style(16.)),
+                    SasIndicator,
+                ));
+            });
+
+            // warnings across the top
+            root.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.,
+                        color: WARNING_COLOR,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Vh(3.),
+                    justify_self: JustifySelf::Center,
+                    ..default()
+                }),
+                WarningReadout,
+            ));
+
+            // flight instruments on the right
+            root.spawn(panel()).with_children(|right| {
+                // attitude: a horizon line that rolls against the rocket
+                right
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Vh(12.),
+                            height: Val::Vh(12.),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            border: UiRect::all(Val::Px(2.)),
+                            overflow: Overflow::clip(),
+                            ..default()
+                        },
+                        border_color: INSTRUMENT_COLOR.into(),
+                        ..default()
+                    })
+                    .with_children(|attitude| {
+                        attitude.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(140.),
+                                    height: Val::Px(2.),
+                                    flex_shrink: 0.,
+                                    ..default()
+                                },
+                                background_color: INSTRUMENT_COLOR.into(),
+                                ..default()
+                            },
+                            AttitudeHorizon,
+                        ));
+                    });
+
+                right.spawn((TextBundle::from_section("", text_style(18.)), FlightReadout));
+
+                // wind: an arrow turning with the wind, seen from above
+                right
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|wind| {
+                        wind.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Vh(4.),
+                                    height: Val::Px(3.),
+                                    justify_content: JustifyContent::FlexEnd,
+                                    ..default()
+                                },
+                                background_color: INSTRUMENT_COLOR.into(),
+                                ..default()
+                            },
+                            WindArrow,
+                        ))
+                        .with_children(|arrow| {
+                            arrow.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Px(8.),
+                                    height: Val::Px(9.),
+                                    margin: UiRect::top(Val::Px(-3.)),
+                                    flex_shrink: 0.,
+                                    ..default()
+                                },
+                                background_color: INSTRUMENT_COLOR.into(),
+                                ..default()
+                            });
+                        });
+                        wind.spawn((TextBundle::from_section("", text_style(18.)), WindReadout));
+                    });
+            });
+        });
+}
+
+fn toggle_hud_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut hud: ResMut<HudSettings>,
+    state: Res<State<GameState>>,
+    mut root_query: Query<&mut Visibility, With<HudRoot>>,
+) {
+    if keyboard_input.just_pressed(settings.controls.hud) {
+        hud.visible = !hud.visible;
+    }
+
+    // only shown over the flight, not the menus
+    let visible = hud.visible && (state.get().is_flying() || *state.get() == GameState::Paused);
+    for mut visibility in root_query.iter_mut() {
+        let wanted = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+fn gauges_system(
+    mission: Res<ActiveMission>,
+    difficulty: Res<ActiveDifficulty>,
+    rocket_query: Query<(&Fuel, &Thrust), With<Rocket>>,
+    mut fuel_gauge: Query<&mut Style, (With<FuelGauge>, Without<ThrottleBar>)>,
+    mut throttle_bar: Query<&mut Style, (With<ThrottleBar>, Without<FuelGauge>)>,
+) {
+    let Ok((fuel, thrust)) = rocket_query.get_single() else {
+        return;
+    };
+
+    let fuel_fraction = fuel.value / mission.0.start.fuel.max(1.0);
+    for mut style in fuel_gauge.iter_mut() {
+        style.height = Val::Percent(fuel_fraction.clamp(0.0, 1.0) * 100.0);
+    }
+    for mut style in throttle_bar.iter_mut() {
+        style.height =
+            Val::Percent((thrust.value / difficulty.0.max_thrust).clamp(0.0, 1.0) * 100.0);
+    }
+}
+
+fn flight_readout_system(
+    gravity: Res<PlanetGravity>,
+    mission: Res<ActiveMission>,
+    trajectory: Res<PredictedTrajectory>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    rocket_query: Query<(&Fuel, &RadarAltimeter), With<Rocket>>,
+    mut readout_query: Query<&mut Text, (With<FlightReadout>, Without<WarningReadout>)>,
+    mut warning_query: Query<&mut Text, (With<WarningReadout>, Without<FlightReadout>)>,
+    mut horizon_query: Query<&mut Transform, (With<AttitudeHorizon>, Without<RocketCollider>)>,
+) {
+    let Ok((transform, velocity)) = body.get_single() else {
+        return;
+    };
+
+    let position = transform.translation;
+    let up = gravity.up_at(position);
+    let vertical_speed = velocity.linvel.dot(up);
+    let horizontal_speed = (velocity.linvel - up * vertical_speed).length();
+    let rocket_up = transform.rotation * Vec3::Y;
+    let tilt = rocket_up.angle_between(up).to_degrees();
+
+    let Ok((fuel, radar)) = rocket_query.get_single() else {
+        return;
+    };
+    let radar_altitude = radar
+        .height
+        .map(|height| format!("{:6.1} m", height))
+        .unwrap_or_else(|| "  ---- m".to_string());
+    let time_to_impact = radar
+        .time_to_impact
+        .map(|seconds| format!("{:5.1} s", seconds))
+        .unwrap_or_else(|| "  --- s".to_string());
+    // predicted touchdown speed, from the trajectory assist
+    let touchdown_speed = trajectory
+        .impact
+        .map(|impact| format!("{:5.1} m/s", impact.speed))
+        .unwrap_or_else(|| "  --- m/s".to_string());
+
+    // roll is the sideways lean, pitch the lean toward or away from the camera
+    let roll = rocket_up.x.atan2(rocket_up.y).to_degrees();
+    let pitch = rocket_up.z.atan2(rocket_up.y).to_degrees();
+
+    for mut text in readout_query.iter_mut() {
+        text.sections[0].value = format!(
+            "ALT {}\nV/S {:+6.1} m/s\nH/S {:6.1} m/s\nTTI {}\nT/D {}\nSLOPE {:4.0}\nROLL {:+5.0}\nPITCH {:+4.0}",
+            radar_altitude,
+            vertical_speed,
+            horizontal_speed,
+            time_to_impact,
+            touchdown_speed,
+            radar.slope_degrees,
+            roll,
+            pitch
+        );
+    }
+
+    for mut horizon in horizon_query.iter_mut() {
+        horizon.rotation = Quat::from_rotation_z(roll.to_radians());
+    }
+
+    let mut warnings = Vec::new();
+    if fuel.value <= 0.0 {
+        warnings.push("FLAMEOUT");
+    } else if fuel.value < mission.0.start.fuel * LOW_FUEL_FRACTION {
+        warnings.push("LOW FUEL");
+    }
+    // the mission's limits, already scaled for the difficulty
+    let criteria = &mission.0.success;
+    if -vertical_speed > criteria.max_touchdown_speed {
+        warnings.push("SINK RATE");
+    }
+    if tilt > criteria.max_tilt_degrees {
+        warnings.push("TILT");
+    }
+    if radar.slope_degrees > MAX_SAFE_SLOPE
+        && radar
+            .height
+            .is_some_and(|height| height < SLOPE_WARNING_HEIGHT)
+    {
+        warnings.push("SLOPE");
+    }
+
+    for mut text in warning_query.iter_mut() {
+        text.sections[0].value = warnings.join("   ");
+    }
+}
+
+fn wind_indicator_system(
+    wind_query: Query<(&WindDirection, &WindSpeed)>,
+    mut arrow_query: Query<&mut Transform, With<WindArrow>>,
+    mut readout_query: Query<&mut Text, With<WindReadout>>,
+) {
+    let Ok((direction, speed)) = wind_query.get_single() else {
+        return;
+    };
+
+    // the chase camera looks down -Z, so world X is screen right
+    let angle = f32::atan2(direction.value.z, direction.value.x);
+    for mut transform in arrow_query.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(-angle);
+    }
+    for mut text in readout_query.iter_mut() {
+        text.sections[0].value = format!("WIND {:4.1} m/s", speed.meters_per_second());
+    }
+}
+
+fn sas_indicator_system(sas: Res<Sas>, mut indicator_query: Query<&mut Text, With<SasIndicator>>) {
+    if !sas.is_changed() {
+        return;
+    }
+
+    for mut text in indicator_query.iter_mut() {
+        text.sections[0].value = sas.mode.label().to_string();
+        text.sections[0].style.color = if sas.mode == SasMode::Off {
+            INACTIVE_COLOR
+        } else {
+            INSTRUMENT_COLOR
+        };
+    }
+}
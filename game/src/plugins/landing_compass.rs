@@ -1,51 +1,283 @@
 use bevy::{pbr::NotShadowCaster, prelude::*};
+use bevy_rapier3d::dynamics::Velocity as BodyVelocity;
+
+use super::{
+    gravity::PlanetGravity,
+    mission::{ActiveMission, SuccessCriteria, TargetPad},
+    rocket::{Rocket, RocketCollider},
+    splash::GameState,
+    trajectory::{predict_trajectory_system, PredictedTrajectory},
+};
+
+// fraction of the mission limits still considered comfortably safe
+const SAFE_MARGIN: f32 = 0.7;
 
 #[derive(Component)]
 pub struct LandingCompass;
 
+/// Arrow on the compass ring pointing at the target pad.
+#[derive(Component)]
+struct CompassArrow;
+
+#[derive(Component)]
+struct CompassReadout;
+
+/// Where the predicted trajectory meets the ground.
+#[derive(Component)]
+pub struct ImpactMarker;
+
+#[derive(Resource)]
+struct CompassMaterial(Handle<StandardMaterial>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApproachSafety {
+    Safe,
+    Marginal,
+    Unsafe,
+}
+
+impl ApproachSafety {
+    pub fn assess(speed: f32, tilt_degrees: f32, criteria: &SuccessCriteria) -> Self {
+        if speed <= criteria.max_touchdown_speed * SAFE_MARGIN
+            && tilt_degrees <= criteria.max_tilt_degrees * SAFE_MARGIN
+        {
+            ApproachSafety::Safe
+        } else if speed <= criteria.max_touchdown_speed && tilt_degrees <= criteria.max_tilt_degrees
+        {
+            ApproachSafety::Marginal
+        } else {
+            ApproachSafety::Unsafe
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            ApproachSafety::Safe => Color::srgb(0.0, 1.0, 0.0),
+            ApproachSafety::Marginal => Color::srgb(1.0, 0.75, 0.0),
+            ApproachSafety::Unsafe => Color::srgb(1.0, 0.1, 0.1),
+        }
+    }
+}
+
 pub struct LandingCompassPlugin;
 
 impl Plugin for LandingCompassPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup);
-        app.add_systems(Update, track_landing_system);
+        app.add_systems(
+            Update,
+            (
+                track_landing_system,
+                approach_safety_system,
+                impact_marker_system.after(predict_trajectory_system),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, compass_visibility_system);
     }
 }
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
+    let material = materials.add(StandardMaterial {
+        base_color: ApproachSafety::Safe.color(),
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+    commands.insert_resource(CompassMaterial(material.clone()));
+
     // span compass as triangle at bottom of screen}
-    commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Torus::new(0.98, 1.0)),
-            material: materials.add(StandardMaterial {
-                base_color: Color::srgba(0.0, 1.0, 0.0, 1.0),
-                unlit: true,
-                cull_mode: None,
-                ..default()
-            }),
-            transform: Transform {
-                translation: Vec3::new(-0.005, 0.0, 0.8),
-                // scale: Vec3::splat(0.8),
-                // rotation: Quat::from_rotation_x(std::f32::consts::PI / 2.0),
+    commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Torus::new(0.98, 1.0)),
+                material: material.clone(),
+                transform: Transform {
+                    translation: Vec3::new(-0.005, 0.0, 0.8),
+                    // scale: Vec3::splat(0.8),
+                    // rotation: Quat::from_rotation_x(std::f32::consts::PI / 2.0),
+                    ..default()
+                },
                 ..default()
             },
+            NotShadowCaster,
+            LandingCompass,
+        ))
+        .with_children(|compass| {
+            // arrow on the ring, turned toward the target pad
+            compass.spawn((
+                PbrBundle {
+                    mesh: meshes.add(Cone {
+                        radius: 0.08,
+                        height: 0.25,
+                    }),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, -1.0)
+                        .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+                    ..default()
+                },
+                NotShadowCaster,
+                CompassArrow,
+            ));
+        });
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Torus::new(0.12, 0.18)),
+            material,
+            visibility: Visibility::Hidden,
             ..default()
         },
         NotShadowCaster,
-        LandingCompass,
+        ImpactMarker,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 22.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(16.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        CompassReadout,
     ));
 }
 
 fn track_landing_system(
+    mission: Res<ActiveMission>,
+    target: Res<TargetPad>,
+    gravity: Res<PlanetGravity>,
     mut compass_query: Query<&mut Transform, (With<LandingCompass>, Without<Camera3d>)>,
     mut camera_query: Query<&Transform, (With<Camera3d>, Without<LandingCompass>)>,
+    rocket_query: Query<&Transform, (With<Rocket>, Without<LandingCompass>, Without<Camera3d>)>,
+    mut readout_query: Query<&mut Text, With<CompassReadout>>,
 ) {
+    let rocket = rocket_query.get_single().ok();
+    let pad = target.spec(&mission.0);
+
     for mut compass_transform in compass_query.iter_mut() {
         for camera_transform in camera_query.iter_mut() {
             compass_transform.translation.y = camera_transform.translation.y - 2.5;
         }
+
+        let (Some(rocket), Some(pad)) = (rocket, pad) else {
+            continue;
+        };
+        compass_transform.translation.x = rocket.translation.x;
+        compass_transform.translation.z = rocket.translation.z;
+
+        let up = gravity.up_at(rocket.translation);
+        let to_pad = pad.position - rocket.translation;
+        let horizontal = to_pad - up * to_pad.dot(up);
+        if horizontal.x != 0.0 || horizontal.z != 0.0 {
+            compass_transform.rotation =
+                Quat::from_rotation_y(f32::atan2(-horizontal.x, -horizontal.z));
+        }
+
+        // bearing is measured clockwise from -Z
+        let bearing = f32::atan2(horizontal.x, -horizontal.z)
+            .to_degrees()
+            .rem_euclid(360.0);
+        for mut text in readout_query.iter_mut() {
+            text.sections[0].value = format!(
+                "{}  {:03.0} deg  {:.1} m",
+                pad.name,
+                bearing,
+                horizontal.length()
+            );
+        }
+    }
+}
+
+fn approach_safety_system(
+    mission: Res<ActiveMission>,
+    gravity: Res<PlanetGravity>,
+    compass_material: Res<CompassMaterial>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut readout_query: Query<&mut Text, With<CompassReadout>>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+) {
+    let Ok((transform, velocity)) = body.get_single() else {
+        return;
+    };
+
+    let up = gravity.up_at(transform.translation);
+    let tilt_degrees = (transform.rotation * Vec3::Y)
+        .angle_between(up)
+        .to_degrees();
+    let safety = ApproachSafety::assess(velocity.linvel.length(), tilt_degrees, &mission.0.success);
+
+    let color = safety.color();
+    if let Some(material) = materials.get_mut(&compass_material.0) {
+        if material.base_color != color {
+            material.base_color = color;
+        }
+    }
+    for mut text in readout_query.iter_mut() {
+        text.sections[0].style.color = color;
+    }
+}
+
+/// The compass only makes sense while flying toward a pad.
+fn compass_visibility_system(
+    state: Res<State<GameState>>,
+    mut compass_query: Query<&mut Visibility, (With<LandingCompass>, Without<CompassReadout>)>,
+    mut readout_query: Query<&mut Visibility, (With<CompassReadout>, Without<LandingCompass>)>,
+    mut marker_query: Query<
+        &mut Visibility,
+        (
+            With<ImpactMarker>,
+            Without<LandingCompass>,
+            Without<CompassReadout>,
+        ),
+    >,
+) {
+    if !state.is_changed() {
+        return;
+    }
+
+    let visibility = if matches!(state.get(), GameState::Playing | GameState::Paused) {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut compass in compass_query.iter_mut() {
+        *compass = visibility;
+    }
+    for mut readout in readout_query.iter_mut() {
+        *readout = visibility;
+    }
+    if visibility == Visibility::Hidden {
+        for mut marker in marker_query.iter_mut() {
+            *marker = Visibility::Hidden;
+        }
+    }
+}
+
+fn impact_marker_system(
+    trajectory: Res<PredictedTrajectory>,
+    mut marker_query: Query<(&mut Transform, &mut Visibility), With<ImpactMarker>>,
+) {
+    for (mut marker_transform, mut visibility) in marker_query.iter_mut() {
+        match trajectory.impact {
+            Some(impact) => {
+                // lie flat on the ground, slightly above it to avoid z-fighting
+                marker_transform.translation = impact.point + impact.normal * 0.03;
+                marker_transform.rotation = Quat::from_rotation_arc(Vec3::Y, impact.normal);
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
     }
 }
@@ -0,0 +1,344 @@
+use bevy::{app::AppExit, log, prelude::*};
+
+use super::{
+    ground_control::GroundCommand,
+    mission::{ActiveMission, LandingResult, MissionCompleted, MissionStarted},
+    settings::Settings,
+    splash::GameState,
+};
+
+// how long the rocket is left to settle before the results come up
+const RESULTS_DELAY: f32 = 3.0;
+
+const BUTTON_COLOR: Color = Color::srgba(0.15, 0.15, 0.15, 0.85);
+const SELECTED_BUTTON_COLOR: Color = Color::srgba(0.6, 0.15, 0.15, 0.9);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Resume,
+    Restart,
+    Settings,
+    MainMenu,
+    Quit,
+}
+
+/// Sent by the Settings button, opens the settings screen.
+#[derive(Event)]
+pub struct OpenSettings;
+
+/// Position of a button in its menu, for keyboard navigation.
+#[derive(Component)]
+struct MenuButton(usize);
+
+/// Highlighted button of whichever menu is open.
+#[derive(Resource, Default)]
+struct MenuSelection(usize);
+
+/// Latest landing, shown on the results screen.
+#[derive(Resource, Default)]
+struct LastResult(Option<LandingResult>);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuSelection>()
+            .init_resource::<LastResult>()
+            .add_event::<OpenSettings>()
+            .add_systems(OnEnter(GameState::Paused), show_pause_menu)
+            .add_systems(OnEnter(GameState::Results), show_results)
+            .add_systems(
+                Update,
+                (
+                    pause_system,
+                    ground_state_system,
+                    mission_completed_system,
+                    results_delay_system,
+                    (menu_navigation_system, menu_action_system).chain(),
+                ),
+            );
+    }
+}
+
+fn pause_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(settings.controls.pause) {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn ground_state_system(
+    mut ground_commands: EventReader<GroundCommand>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for command in ground_commands.read() {
+        match (command, state.get()) {
+            (GroundCommand::Pause, GameState::Playing) => next_state.set(GameState::Paused),
+            (GroundCommand::Resume, GameState::Paused) => next_state.set(GameState::Playing),
+            // a reset puts the mission back at its start, see `restart_mission_system`
+            (GroundCommand::Reset, state) if state.accepts_reset() => {
+                next_state.set(GameState::Playing)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn mission_completed_system(
+    mut mission_completed: EventReader<MissionCompleted>,
+    mut last_result: ResMut<LastResult>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for MissionCompleted(result) in mission_completed.read() {
+        last_result.0 = Some(result.clone());
+        if *state.get() == GameState::Playing {
+            next_state.set(if result.success {
+                GameState::Landed
+            } else {
+                GameState::Crashed
+            });
+        }
+    }
+}
+
+fn results_delay_system(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if state.is_changed() {
+        *elapsed = 0.0;
+    }
+    if !matches!(state.get(), GameState::Landed | GameState::Crashed) {
+        return;
+    }
+
+    *elapsed += time.delta_seconds();
+    if *elapsed > RESULTS_DELAY || keyboard_input.just_pressed(KeyCode::Enter) {
+        next_state.set(GameState::Results);
+    }
+}
+
+/// Centred column with a heading and one button per action.
+fn spawn_menu(
+    commands: &mut Commands,
+    selection: &mut MenuSelection,
+    state: GameState,
+    heading: &str,
+    body: &str,
+    actions: &[(MenuAction, &str)],
+) {
+    selection.0 = 0;
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.5).into(),
+                ..default()
+            },
+            StateScoped(state),
+        ))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                heading,
+                TextStyle {
+                    font_size: 64.,
+                    color: Color::srgb(0.8, 0.2, 0.2),
+                    ..default()
+                },
+            ));
+
+            if !body.is_empty() {
+                menu.spawn(
+                    TextBundle::from_section(
+                        body,
+                        TextStyle {
+                            font_size: 22.,
+                            ..default()
+                        },
+                    )
+                    .with_text_justify(JustifyText::Center),
+                );
+            }
+
+            for (index, (action, label)) in actions.iter().enumerate() {
+                menu.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(240.),
+                            padding: UiRect::all(Val::Px(10.)),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON_COLOR.into(),
+                        ..default()
+                    },
+                    MenuButton(index),
+                    *action,
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        *label,
+                        TextStyle {
+                            font_size: 26.,
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        });
+}
+
+fn show_pause_menu(mut commands: Commands, mut selection: ResMut<MenuSelection>) {
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        GameState::Paused,
+        "Paused",
+        "",
+        &[
+            (MenuAction::Resume, "Resume"),
+            (MenuAction::Restart, "Restart"),
+            (MenuAction::Settings, "Settings"),
+            (MenuAction::Quit, "Quit"),
+        ],
+    );
+}
+
+fn show_results(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    mission: Res<ActiveMission>,
+    last_result: Res<LastResult>,
+) {
+    let (heading, body) = match &last_result.0 {
+        Some(result) => (
+            if result.success {
+                "Mission complete"
+            } else {
+                "Mission failed"
+            },
+            format!(
+                "{}\n\nTouchdown {:.2} m/s, tilt {:.1} deg\nPad: {}\nFuel left: {:.0}\nScore: {} (x{:.1})",
+                mission.0.name,
+                result.speed,
+                result.tilt_degrees,
+                result.pad.as_deref().unwrap_or("none"),
+                result.fuel,
+                result.score,
+                result.score_multiplier
+            ),
+        ),
+        None => ("Mission over", mission.0.name.clone()),
+    };
+
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        GameState::Results,
+        heading,
+        &body,
+        &[
+            (MenuAction::Restart, "Retry"),
+            (MenuAction::MainMenu, "Main menu"),
+            (MenuAction::Quit, "Quit"),
+        ],
+    );
+}
+
+fn menu_navigation_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<MenuSelection>,
+    mut buttons: Query<(&MenuButton, &Interaction, &mut BackgroundColor)>,
+) {
+    let count = buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    for (button, interaction, _) in buttons.iter() {
+        if *interaction == Interaction::Hovered {
+            selection.0 = button.0;
+        }
+    }
+
+    for (button, _, mut color) in buttons.iter_mut() {
+        *color = if button.0 == selection.0 {
+            SELECTED_BUTTON_COLOR.into()
+        } else {
+            BUTTON_COLOR.into()
+        };
+    }
+}
+
+fn menu_action_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    selection: Res<MenuSelection>,
+    buttons: Query<(&MenuButton, &MenuAction)>,
+    clicked: Query<(&MenuAction, &Interaction), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut mission_started: EventWriter<MissionStarted>,
+    mut open_settings: EventWriter<OpenSettings>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let clicked = clicked
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Pressed)
+        .map(|(action, _)| action);
+    let chosen = if keyboard_input.just_pressed(KeyCode::Enter) {
+        buttons
+            .iter()
+            .find(|(button, _)| button.0 == selection.0)
+            .map(|(_, action)| action)
+    } else {
+        None
+    };
+    let Some(action) = clicked.or(chosen) else {
+        return;
+    };
+
+    log::info!("Menu action {:?}", action);
+    match action {
+        MenuAction::Resume => next_state.set(GameState::Playing),
+        MenuAction::Restart => {
+            mission_started.send(MissionStarted);
+            next_state.set(GameState::Playing);
+        }
+        MenuAction::Settings => {
+            open_settings.send(OpenSettings);
+        }
+        MenuAction::MainMenu => next_state.set(GameState::MainMenu),
+        MenuAction::Quit => {
+            app_exit.send(AppExit::Success);
+        }
+    }
+}
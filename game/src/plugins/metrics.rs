@@ -0,0 +1,239 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    log,
+    prelude::*,
+};
+use bevy_rapier3d::dynamics::Velocity as BodyVelocity;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::{
+    altimeter::RadarAltimeter,
+    exhaust::{ParticlePool, ParticleStats},
+    gravity::PlanetGravity,
+    rocket::{Altitute, Fuel, MissionTime, Rocket, RocketCollider, Thrust},
+    weather::WindSpeed,
+};
+
+#[derive(Default, Clone, Copy)]
+struct MetricsSnapshot {
+    fuel: f32,
+    altitude: f32,
+    radar_altitude: Option<f32>,
+    vertical_speed: f32,
+    horizontal_speed: f32,
+    thrust: f32,
+    wind_speed: f32,
+    mission_time: f32,
+    fps: f64,
+    entities: f64,
+    particles: usize,
+    particles_spawned: u64,
+}
+
+impl MetricsSnapshot {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        gauge(&mut out, "fuel", "Remaining fuel.", self.fuel as f64);
+        gauge(
+            &mut out,
+            "altitude",
+            "Rocket altitude.",
+            self.altitude as f64,
+        );
+        gauge(
+            &mut out,
+            "radar_altitude",
+            "Height above the ground below, NaN when out of range.",
+            self.radar_altitude.map_or(f64::NAN, |height| height as f64),
+        );
+        gauge(
+            &mut out,
+            "vertical_speed",
+            "Vertical speed, positive when climbing.",
+            self.vertical_speed as f64,
+        );
+        gauge(
+            &mut out,
+            "horizontal_speed",
+            "Horizontal speed.",
+            self.horizontal_speed as f64,
+        );
+        gauge(
+            &mut out,
+            "thrust",
+            "Main engine thrust.",
+            self.thrust as f64,
+        );
+        gauge(
+            &mut out,
+            "wind_speed",
+            "Wind speed.",
+            self.wind_speed as f64,
+        );
+        gauge(
+            &mut out,
+            "mission_time_seconds",
+            "Time since launch.",
+            self.mission_time as f64,
+        );
+        gauge(&mut out, "fps", "Smoothed frames per second.", self.fps);
+        gauge(
+            &mut out,
+            "entities",
+            "Number of live entities.",
+            self.entities,
+        );
+        gauge(
+            &mut out,
+            "particles",
+            "Number of live exhaust particles.",
+            self.particles as f64,
+        );
+        counter(
+            &mut out,
+            "particles_spawned_total",
+            "Exhaust particles spawned since start.",
+            self.particles_spawned as f64,
+        );
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    metric(out, "gauge", name, help, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: f64) {
+    metric(out, "counter", name, help, value);
+}
+
+fn metric(out: &mut String, kind: &str, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP red_horizon_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE red_horizon_{} {}", name, kind);
+    let _ = writeln!(out, "red_horizon_{} {}", name, value);
+}
+
+#[derive(Resource, Clone, Default)]
+struct MetricsRegistry {
+    snapshot: Arc<Mutex<MetricsSnapshot>>,
+}
+
+pub struct MetricsPlugin {
+    pub address: String,
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+
+        let registry = MetricsRegistry::default();
+        tokio::spawn(serve_metrics(self.address.clone(), registry.clone()));
+
+        app.insert_resource(registry)
+            .add_systems(Update, collect_metrics_system);
+    }
+}
+
+fn collect_metrics_system(
+    registry: Res<MetricsRegistry>,
+    gravity: Res<PlanetGravity>,
+    diagnostics: Res<DiagnosticsStore>,
+    mission_time: Res<MissionTime>,
+    particle_stats: Res<ParticleStats>,
+    particle_pool: Res<ParticlePool>,
+    rocket_query: Query<(&Fuel, &Altitute, &RadarAltimeter, &Thrust), With<Rocket>>,
+    body_query: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    wind_query: Query<&WindSpeed>,
+) {
+    let Ok(mut snapshot) = registry.snapshot.lock() else {
+        return;
+    };
+
+    for (fuel, altitude, radar, thrust) in rocket_query.iter() {
+        snapshot.fuel = fuel.value;
+        snapshot.altitude = altitude.value;
+        snapshot.radar_altitude = radar.height;
+        snapshot.thrust = thrust.value;
+    }
+
+    for (transform, velocity) in body_query.iter() {
+        let up = gravity.up_at(transform.translation);
+        let vertical_speed = velocity.linvel.dot(up);
+        snapshot.vertical_speed = vertical_speed;
+        snapshot.horizontal_speed = (velocity.linvel - up * vertical_speed).length();
+    }
+
+    for wind_speed in wind_query.iter() {
+        snapshot.wind_speed = wind_speed.value;
+    }
+
+    snapshot.mission_time = mission_time.seconds;
+    snapshot.particles = particle_pool.active();
+    snapshot.particles_spawned = particle_stats.spawned;
+    snapshot.fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    snapshot.entities = diagnostics
+        .get(&EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+        .and_then(|count| count.value())
+        .unwrap_or_default();
+}
+
+async fn serve_metrics(address: String, registry: MetricsRegistry) {
+    let listener = match TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to bind metrics endpoint on {}: {:?}", address, e);
+            return;
+        }
+    };
+    log::info!("Serving metrics on http://{}/metrics", address);
+
+    loop {
+        match listener.accept().await {
+            Ok((socket, _addr)) => {
+                tokio::spawn(handle_scrape(socket, registry.clone()));
+            }
+            Err(e) => {
+                log::error!("Failed to accept metrics connection: {:?}", e);
+            }
+        }
+    }
+}
+
+async fn handle_scrape(mut socket: TcpStream, registry: MetricsRegistry) {
+    // only the request line matters, so a single read is enough
+    let mut request = [0; 1024];
+    let Ok(n) = socket.read(&mut request).await else {
+        return;
+    };
+    let request = String::from_utf8_lossy(&request[..n]);
+
+    let response = if request.starts_with("GET /metrics ") {
+        let snapshot = registry
+            .snapshot
+            .lock()
+            .map(|snapshot| *snapshot)
+            .unwrap_or_default();
+        let body = snapshot.render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    let _ = socket.write_all(response.as_bytes()).await;
+}
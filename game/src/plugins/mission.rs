@@ -0,0 +1,433 @@
+use std::path::Path;
+
+use bevy::{asset::io::file::FileAssetReader, log, prelude::*};
+use serde_derive::Deserialize;
+
+use super::{
+    daylight::Daylight,
+    difficulty::ActiveDifficulty,
+    environment::{EnvironmentManifest, MissionEnvironment, ENVIRONMENT_MANIFEST},
+    gravity::PlanetGravity,
+    ground_control::GroundCommand,
+    rocket::{FlightEvent, Fuel, Rocket, RocketCollider, START_ALTITUDE, START_FUEL},
+    settings::Settings,
+    splash::GameState,
+    terrain::{LandingPad, TerrainSource},
+};
+
+/// Missions are read from here, relative to the asset folder.
+pub const MISSIONS_DIRECTORY: &str = "missions";
+
+/// A landing scenario, loaded from a JSON file in `assets/missions`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Mission {
+    pub name: String,
+    pub description: String,
+    pub terrain: TerrainSource,
+    pub pads: Vec<PadSpec>,
+    pub start: StartState,
+    /// Overrides the live Mars weather when set.
+    pub wind: Option<WindProfile>,
+    pub daylight: Daylight,
+    pub environment: MissionEnvironment,
+    pub success: SuccessCriteria,
+}
+
+impl Default for Mission {
+    fn default() -> Self {
+        Self {
+            name: "Training".to_string(),
+            description: "Put it down on the pad.".to_string(),
+            terrain: TerrainSource::default(),
+            pads: vec![PadSpec::default()],
+            start: StartState::default(),
+            wind: None,
+            daylight: Daylight::default(),
+            environment: MissionEnvironment::default(),
+            success: SuccessCriteria::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PadSpec {
+    pub name: String,
+    pub position: Vec3,
+    pub radius: f32,
+    /// Smaller or harder to reach pads are worth more.
+    pub score_multiplier: f32,
+    /// Draw the pad. The hand-made terrain already has one modelled.
+    pub marker: bool,
+}
+
+impl Default for PadSpec {
+    fn default() -> Self {
+        Self {
+            name: "Launch Pad".to_string(),
+            position: Vec3::ZERO,
+            radius: 1.2,
+            score_multiplier: 1.0,
+            marker: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct StartState {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Initial lean around the Z axis.
+    pub tilt_degrees: f32,
+    pub fuel: f32,
+}
+
+impl Default for StartState {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, START_ALTITUDE, 0.0),
+            velocity: Vec3::ZERO,
+            tilt_degrees: 0.0,
+            fuel: START_FUEL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct WindProfile {
+    pub speed_kph: f32,
+    pub degrees: f32,
+    /// Extra speed that comes and goes on top of `speed_kph`.
+    pub gust_kph: f32,
+    pub gust_period: f32,
+    /// Airborne dust, 0 for clear air to 1 for a full storm.
+    pub dust_opacity: f32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct SuccessCriteria {
+    pub max_touchdown_speed: f32,
+    pub max_tilt_degrees: f32,
+    /// Must the rocket come down on a pad, or anywhere on the ground?
+    pub require_pad: bool,
+}
+
+impl Default for SuccessCriteria {
+    fn default() -> Self {
+        Self {
+            max_touchdown_speed: 2.0,
+            max_tilt_degrees: 15.0,
+            require_pad: true,
+        }
+    }
+}
+
+/// All missions found on disk, in file name order.
+#[derive(Resource)]
+pub struct MissionCatalog {
+    pub missions: Vec<Mission>,
+}
+
+impl MissionCatalog {
+    /// Reads every mission in `directory`, resolving planets picked from the
+    /// manifest. A terrain seed from the settings replaces each mission's
+    /// terrain with a procedural one.
+    pub fn load(
+        directory: &Path,
+        manifest: &EnvironmentManifest,
+        terrain_seed: Option<u64>,
+    ) -> Self {
+        let mut paths: Vec<_> = match std::fs::read_dir(directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(e) => {
+                log::warn!("Failed to read missions from {:?}: {:?}", directory, e);
+                Vec::new()
+            }
+        };
+        paths.sort();
+
+        let mut missions: Vec<Mission> = paths
+            .iter()
+            .filter_map(|path| match Self::load_file(path) {
+                Ok(mission) => Some(mission),
+                Err(e) => {
+                    log::error!("Skipping mission {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+
+        if missions.is_empty() {
+            missions.push(Mission::default());
+        }
+
+        for mission in missions.iter_mut() {
+            let Some(planet) = &mission.environment.planet else {
+                continue;
+            };
+            match manifest.planets.get(planet) {
+                Some(terrain) => mission.terrain = terrain.clone(),
+                None => log::error!("Mission {} uses unknown planet {}", mission.name, planet),
+            }
+        }
+
+        if let Some(seed) = terrain_seed {
+            for mission in missions.iter_mut() {
+                mission.terrain = TerrainSource::Procedural { seed };
+            }
+        }
+
+        Self { missions }
+    }
+
+    fn load_file(path: &Path) -> Result<Mission, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mission: Mission = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        if mission.pads.is_empty() {
+            return Err("a mission needs at least one pad".to_string());
+        }
+        Ok(mission)
+    }
+}
+
+/// Mission being flown, or shown behind the title screen.
+#[derive(Resource, Default)]
+pub struct ActiveMission(pub Mission);
+
+/// Index into the catalog picked on the title screen.
+#[derive(Resource, Default)]
+pub struct SelectedMission(pub usize);
+
+/// Index of the pad in `ActiveMission` the pilot is aiming for.
+#[derive(Resource, Default)]
+pub struct TargetPad(pub usize);
+
+impl TargetPad {
+    pub fn spec<'a>(&self, mission: &'a Mission) -> Option<&'a PadSpec> {
+        mission.pads.get(self.0)
+    }
+}
+
+/// Sent when `ActiveMission` should be (re)applied to the world.
+#[derive(Event)]
+pub struct MissionStarted;
+
+#[derive(Debug, Clone)]
+pub struct LandingResult {
+    pub success: bool,
+    pub speed: f32,
+    pub tilt_degrees: f32,
+    pub pad: Option<String>,
+    pub score_multiplier: f32,
+    pub fuel: f32,
+    pub score: u32,
+}
+
+#[derive(Event)]
+pub struct MissionCompleted(pub LandingResult);
+
+pub struct MissionPlugin;
+
+impl Plugin for MissionPlugin {
+    fn build(&self, app: &mut App) {
+        let assets = FileAssetReader::get_base_path().join("assets");
+        let manifest = EnvironmentManifest::load(&assets.join(ENVIRONMENT_MANIFEST));
+        let catalog = MissionCatalog::load(
+            &assets.join(MISSIONS_DIRECTORY),
+            &manifest,
+            app.world().resource::<Settings>().terrain_seed,
+        );
+        log::info!("Loaded {} missions", catalog.missions.len());
+        let difficulty = app.world().resource::<Settings>().difficulty.preset();
+
+        app.insert_resource(ActiveMission(difficulty.apply(&catalog.missions[0])))
+            .insert_resource(ActiveDifficulty(difficulty))
+            .insert_resource(catalog)
+            .insert_resource(manifest)
+            .init_resource::<SelectedMission>()
+            .init_resource::<TargetPad>()
+            .add_event::<MissionStarted>()
+            .add_event::<MissionCompleted>()
+            .add_systems(Startup, start_mission)
+            .add_systems(
+                Update,
+                (
+                    restart_mission_system,
+                    evaluate_landing_system,
+                    show_landing_result_system,
+                ),
+            )
+            .add_systems(
+                Update,
+                cycle_target_pad_system.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn start_mission(mut mission_started: EventWriter<MissionStarted>) {
+    mission_started.send(MissionStarted);
+}
+
+fn cycle_target_pad_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mission: Res<ActiveMission>,
+    mut target: ResMut<TargetPad>,
+    mut mission_started: EventReader<MissionStarted>,
+) {
+    if mission_started.read().count() > 0 {
+        target.0 = 0;
+    }
+
+    if keyboard_input.just_pressed(settings.controls.target_pad) && !mission.0.pads.is_empty() {
+        target.0 = (target.0 + 1) % mission.0.pads.len();
+        log::info!("Targeting {}", mission.0.pads[target.0].name);
+    }
+}
+
+fn restart_mission_system(
+    mut ground_commands: EventReader<GroundCommand>,
+    state: Res<State<GameState>>,
+    mut mission_started: EventWriter<MissionStarted>,
+) {
+    for command in ground_commands.read() {
+        if let GroundCommand::Reset = command {
+            if !state.get().accepts_reset() {
+                log::warn!("Ignoring ground reset from {:?}", state.get());
+                continue;
+            }
+            mission_started.send(MissionStarted);
+        }
+    }
+}
+
+fn evaluate_landing_system(
+    mission: Res<ActiveMission>,
+    gravity: Res<PlanetGravity>,
+    mut evaluated: Local<bool>,
+    mut mission_started: EventReader<MissionStarted>,
+    mut flight_events: EventReader<FlightEvent>,
+    mut mission_completed: EventWriter<MissionCompleted>,
+    body: Query<&Transform, With<RocketCollider>>,
+    pads: Query<(&Name, &LandingPad, &Transform), Without<RocketCollider>>,
+    fuel: Query<&Fuel, With<Rocket>>,
+) {
+    if mission_started.read().count() > 0 {
+        *evaluated = false;
+    }
+
+    let Ok(transform) = body.get_single() else {
+        return;
+    };
+
+    for event in flight_events.read() {
+        let FlightEvent::Touchdown { speed } = *event else {
+            continue;
+        };
+        // only the first touchdown counts, bounces don't
+        if *evaluated {
+            continue;
+        }
+        *evaluated = true;
+
+        let position = transform.translation;
+        let up = gravity.up_at(position);
+        let tilt_degrees = (transform.rotation * Vec3::Y)
+            .angle_between(up)
+            .to_degrees();
+        // the first contact may be the ground around a pad rather than the
+        // pad itself, so the pad is the one the rocket came down within
+        let pad = pads
+            .iter()
+            .map(|(name, pad, pad_transform)| {
+                let offset = position - pad_transform.translation;
+                let across = (offset - up * offset.dot(up)).length();
+                (name, pad, across)
+            })
+            .filter(|(_, pad, across)| *across <= pad.radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(name, pad, _)| (name, pad));
+        let fuel = fuel.get_single().map(|fuel| fuel.value).unwrap_or_default();
+
+        let criteria = &mission.0.success;
+        let success = speed <= criteria.max_touchdown_speed
+            && tilt_degrees <= criteria.max_tilt_degrees
+            && (pad.is_some() || !criteria.require_pad);
+        let score = if success {
+            let softness = 1.0 - speed / criteria.max_touchdown_speed.max(0.01);
+            let multiplier = pad.map(|(_, pad)| pad.score_multiplier).unwrap_or(1.0);
+            ((fuel + 500.0 * softness) * multiplier) as u32
+        } else {
+            0
+        };
+
+        let result = LandingResult {
+            success,
+            speed,
+            tilt_degrees,
+            pad: pad.map(|(name, _)| name.to_string()),
+            score_multiplier: pad.map(|(_, pad)| pad.score_multiplier).unwrap_or(1.0),
+            fuel,
+            score,
+        };
+        log::info!("Mission {:?} finished: {:?}", mission.0.name, result);
+        mission_completed.send(MissionCompleted(result));
+    }
+}
+
+fn show_landing_result_system(
+    mut commands: Commands,
+    mut mission_completed: EventReader<MissionCompleted>,
+) {
+    for MissionCompleted(result) in mission_completed.read() {
+        // shown while the rocket settles, until the results screen
+        let (headline, color, state) = if result.success {
+            ("Landed", Color::srgb(0.3, 0.9, 0.3), GameState::Landed)
+        } else {
+            ("Crashed", Color::srgb(0.9, 0.2, 0.2), GameState::Crashed)
+        };
+        let details = format!(
+            "{} at {:.1} m/s, {:.0} deg tilt - score {} (x{:.1})",
+            result.pad.as_deref().unwrap_or("Off pad"),
+            result.speed,
+            result.tilt_degrees,
+            result.score,
+            result.score_multiplier
+        );
+
+        commands.spawn((
+            TextBundle::from_sections([
+                TextSection::new(
+                    format!("{}\n", headline),
+                    TextStyle {
+                        font_size: 64.,
+                        color,
+                        ..default()
+                    },
+                ),
+                TextSection::new(
+                    details,
+                    TextStyle {
+                        font_size: 24.,
+                        ..default()
+                    },
+                ),
+            ])
+            .with_text_justify(JustifyText::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(30.),
+                justify_self: JustifySelf::Center,
+                ..default()
+            }),
+            StateScoped(state),
+        ));
+    }
+}
@@ -1,8 +1,23 @@
+pub mod altimeter;
 pub mod camera;
+pub mod daylight;
+pub mod difficulty;
+pub mod dust;
 pub mod environment;
+pub mod exhaust;
+pub mod gravity;
+pub mod ground_control;
+pub mod hud;
 pub mod landing_compass;
+pub mod menu;
+pub mod metrics;
+pub mod mission;
 pub mod rocket;
+pub mod sas;
+pub mod settings;
 pub mod splash;
-// pub mod telemetry;
+pub mod telemetry;
 pub mod terrain;
+pub mod terrain_generator;
+pub mod trajectory;
 pub mod weather;
@@ -1,10 +1,17 @@
 use bevy::{audio::PlaybackMode, prelude::*};
 
-use bevy_rapier3d::prelude::*;
-use rand::Rng;
+use bevy_rapier3d::{dynamics::Velocity as BodyVelocity, prelude::*};
+use serde_derive::Serialize;
 
 use super::{
-    splash::GameState,
+    altimeter::RadarAltimeter,
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    ground_control::{EcsSide, GroundCommand},
+    mission::{ActiveMission, MissionStarted},
+    sas::sas_system,
+    settings::Settings,
+    splash::{in_flight, GameState},
     weather::{WindDirection, WindSpeed},
 };
 
@@ -49,35 +56,80 @@ struct RocketSoundEffect;
 #[derive(Component)]
 pub struct RocketCollider;
 
-#[derive(Component)]
-struct Particle {
-    position: Vec3,
-    velocity: Vec3,
-    lifetime: f32,
-    scale: f32,
-    rotation: Quat,
+/// Time spent flying since launch or the last reset.
+#[derive(Resource, Default)]
+pub struct MissionTime {
+    pub seconds: f32,
+}
+
+/// Discrete things that happen during a flight.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum FlightEvent {
+    Touchdown { speed: f32 },
+    Liftoff,
+    Flameout,
+}
+
+/// Controls requested by a ground station, applied on top of the keyboard.
+#[derive(Resource, Default)]
+pub struct RemoteControl {
+    pub throttle: Option<f32>,
+    pub left_ecs_burn: f32,
+    pub right_ecs_burn: f32,
 }
 
-const MAX_THRUST: f32 = 6.5;
-const MAX_ECS: f32 = 3.0;
+/// Holds a gentle descent rate by driving the main engine.
+#[derive(Resource, Default)]
+pub struct Autopilot {
+    pub enabled: bool,
+}
+
+/// Fuel a mission starts with on Normal, unless it says otherwise.
+pub const START_FUEL: f32 = 1000.0;
+// 0.4 m cube at a density of 20
+pub const ROCKET_MASS: f32 = 1.28;
+const AUTOPILOT_GAIN: f32 = 2.0;
+pub const LINEAR_DAMPING: f32 = 1.5;
+/// How fast a held thruster builds up, per second.
+pub const ECS_RAMP: f32 = 0.1;
+// seconds off the ground before a bounce counts as a liftoff
+const LIFTOFF_DEBOUNCE: f32 = 0.5;
 pub const START_ALTITUDE: f32 = 26.75; // 36.0;
 
 impl Plugin for RocketPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<RemoteControl>()
+            .init_resource::<Autopilot>()
+            .init_resource::<MissionTime>()
+            .add_event::<FlightEvent>();
         app.add_systems(Startup, (setup_assets, setup_collider_body));
+        app.add_systems(Update, (ground_command_system, apply_mission_system));
         app.add_systems(
             Update,
             (
-                rocket_physics_system,
                 keyboard_control_system,
-                applied_physics_forces_system,
+                autopilot_system.after(keyboard_control_system),
+                mission_clock_system,
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+        // the rocket keeps falling and settling after touchdown, without a pilot
+        app.add_systems(
+            Update,
+            (
+                rocket_physics_system,
+                applied_physics_forces_system
+                    .after(autopilot_system)
+                    .after(sas_system),
                 rocket_fuel_system,
-                update_particle_system,
-                particle_emitter_system,
                 engine_sound_system,
+                flight_event_system,
             )
-                .run_if(in_state(GameState::Playing)),
+                .run_if(in_flight),
         );
+        app.add_systems(OnEnter(GameState::Landed), cut_engines);
+        app.add_systems(OnEnter(GameState::Crashed), cut_engines);
+        app.add_systems(OnExit(GameState::Playing), silence_engine);
     }
 }
 
@@ -93,13 +145,14 @@ fn setup_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         })
         .insert(Thrust { value: 0.0 })
-        .insert(Fuel { value: 1000.0 })
+        .insert(Fuel { value: START_FUEL })
         .insert(Velocity {
             value: Vec3::new(0.0, 0.0, 0.0),
         })
         .insert(LeftEcs { value: 0.0 })
         .insert(RightEcs { value: 0.0 })
         .insert(Altitute { value: 0.0 })
+        .insert(RadarAltimeter::default())
         .insert(Rocket);
 
     commands.spawn((
@@ -124,9 +177,10 @@ fn setup_collider_body(mut commands: Commands) {
             force: Vec3::new(0.0, 0.0, 0.0),
             torque: Vec3::new(0.0, 0.0, 0.0),
         })
-        .insert(ColliderMassProperties::Density(20.0))
+        .insert(ColliderMassProperties::Mass(ROCKET_MASS))
+        .insert(BodyVelocity::zero())
         .insert(Damping {
-            linear_damping: 1.5,
+            linear_damping: LINEAR_DAMPING,
             angular_damping: 1.0,
         })
         .insert(TransformBundle::from(Transform::from_xyz(
@@ -138,13 +192,15 @@ fn setup_collider_body(mut commands: Commands) {
 }
 
 fn engine_sound_system(
+    settings: Res<Settings>,
+    difficulty: Res<ActiveDifficulty>,
     music_controller: Query<&AudioSink, With<RocketSoundEffect>>,
     mut _thrust: Query<&mut Thrust, With<Rocket>>,
 ) {
     let thrust = _thrust.single_mut().value;
     if let Ok(sink) = music_controller.get_single() {
-        sink.set_volume(thrust / MAX_THRUST);
-        // sink.set_speed(1.0 + thrust / MAX_THRUST);
+        sink.set_volume(thrust / difficulty.0.max_thrust * settings.volume);
+        // sink.set_speed(1.0 + thrust / difficulty.0.max_thrust);
         if thrust == 0.0 {
             sink.pause();
         } else {
@@ -153,90 +209,21 @@ fn engine_sound_system(
     }
 }
 
-fn particle_emitter_system(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut _rocket_transform: Query<&Transform, With<Rocket>>,
-    mut _thrust: Query<&mut Thrust, With<Rocket>>,
+fn cut_engines(
+    mut remote: ResMut<RemoteControl>,
+    mut _engines: Query<(&mut Thrust, &mut LeftEcs, &mut RightEcs), With<Rocket>>,
 ) {
-    let player_translation = _rocket_transform.single_mut().translation;
-    let thrust = _thrust.single_mut().value;
-    let is_thrusting = thrust > 0.0;
-    let thrust_percentage = thrust / MAX_THRUST;
-    let num_particles = (8.0 * thrust_percentage) as i32;
-
-    if !is_thrusting {
-        return;
-    }
-
-    for _ in 0..num_particles {
-        let position = player_translation;
-        let velocity = Vec3::new(
-            rand::thread_rng().gen_range(-0.45..0.45),
-            if is_thrusting {
-                rand::thread_rng().gen_range(2.5..((thrust / 5.0) + 2.5)) * -1.0
-            } else {
-                0.5
-            },
-            rand::thread_rng().gen_range(-0.45..0.45),
-        );
-        let scale = rand::thread_rng().gen_range(0.01..0.1);
-        // random rotation
-        let rotation =
-            Quat::from_rotation_y(rand::thread_rng().gen_range(0.0..std::f32::consts::PI));
-        let lifetime = 2.5;
-        let color = Color::srgba(1.0, 1.0, 1.0, 0.5);
-
-        commands
-            .spawn(PbrBundle {
-                mesh: meshes.add(Sphere::default().mesh().uv(3, 3)),
-                material: materials.add(StandardMaterial {
-                    base_color: color,
-                    cull_mode: None,
-                    ..default()
-                }),
-                transform: Transform {
-                    translation: position,
-                    scale: Vec3::splat(scale),
-                    ..default()
-                },
-
-                ..default()
-            })
-            .insert(Particle {
-                position,
-                velocity,
-                lifetime,
-                scale,
-                rotation,
-            });
+    *remote = RemoteControl::default();
+    for (mut thrust, mut left_ecs, mut right_ecs) in _engines.iter_mut() {
+        thrust.value = 0.0;
+        left_ecs.value = 0.0;
+        right_ecs.value = 0.0;
     }
 }
 
-fn update_particle_system(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut particles: Query<(Entity, &mut Particle)>,
-    mut transforms: Query<&mut Transform>,
-) {
-    for (entity, mut particle) in particles.iter_mut() {
-        let mut velocity = particle.velocity;
-
-        if particle.position.y < 0.35 {
-            velocity.y = 0.0;
-        }
-
-        particle.position += velocity * time.delta_seconds();
-        particle.lifetime -= time.delta_seconds();
-        particle.scale += time.delta_seconds() * 0.1;
-        transforms.get_mut(entity).unwrap().translation = particle.position;
-        transforms.get_mut(entity).unwrap().scale = Vec3::splat(particle.scale);
-        transforms.get_mut(entity).unwrap().rotation = particle.rotation;
-
-        if particle.lifetime <= 0.0 {
-            commands.entity(entity).despawn()
-        }
+fn silence_engine(music_controller: Query<&AudioSink, With<RocketSoundEffect>>) {
+    if let Ok(sink) = music_controller.get_single() {
+        sink.pause();
     }
 }
 
@@ -254,6 +241,7 @@ fn rocket_fuel_system(
 }
 
 fn rocket_physics_system(
+    gravity: Res<PlanetGravity>,
     mut rocket: Query<&mut Transform, With<Rocket>>,
     mut collider: Query<&mut Transform, (With<RocketCollider>, Without<Rocket>)>,
     mut _altitude: Query<&mut Altitute, With<Rocket>>,
@@ -265,39 +253,218 @@ fn rocket_physics_system(
                 ((body.translation - transform.translation) * 1000.0).round() / 10.0;
             transform.translation = body.translation;
             transform.rotation = body.rotation;
-            _altitude.single_mut().value = body.translation.y;
+            _altitude.single_mut().value = gravity.altitude_at(body.translation);
         }
     }
 }
 
-fn keyboard_control_system(
+pub fn keyboard_control_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    difficulty: Res<ActiveDifficulty>,
     time: Res<Time>,
+    mut remote: ResMut<RemoteControl>,
     mut _engines: Query<(&mut Thrust, &mut LeftEcs, &mut RightEcs), With<Rocket>>,
 ) {
+    remote.left_ecs_burn = (remote.left_ecs_burn - time.delta_seconds()).max(0.0);
+    remote.right_ecs_burn = (remote.right_ecs_burn - time.delta_seconds()).max(0.0);
+    let (max_thrust, max_ecs) = (difficulty.0.max_thrust, difficulty.0.max_ecs);
+
     for (mut thrust, mut left_ecs, mut right_ecs) in _engines.iter_mut() {
-        if keyboard_input.pressed(KeyCode::Space) {
-            thrust.value = MAX_THRUST.min(thrust.value + 2.0 * time.delta_seconds());
+        if keyboard_input.pressed(settings.controls.throttle) {
+            thrust.value = max_thrust.min(thrust.value + 2.0 * time.delta_seconds());
+        } else if let Some(throttle) = remote.throttle {
+            let target = throttle * max_thrust;
+            thrust.value = if thrust.value < target {
+                target.min(thrust.value + 2.0 * time.delta_seconds())
+            } else {
+                target.max(thrust.value - 3.0 * time.delta_seconds())
+            };
         } else {
             thrust.value = 0.0_f32.max(thrust.value - 3.0 * time.delta_seconds());
         }
 
-        if keyboard_input.pressed(KeyCode::KeyA) {
-            left_ecs.value = (left_ecs.value + 0.1 * time.delta_seconds()).min(MAX_ECS);
+        if keyboard_input.pressed(settings.controls.left_ecs) || remote.left_ecs_burn > 0.0 {
+            left_ecs.value = (left_ecs.value + ECS_RAMP * time.delta_seconds()).min(max_ecs);
         } else {
             left_ecs.value = 0.0;
         }
 
-        if keyboard_input.pressed(KeyCode::KeyD) {
-            right_ecs.value = (right_ecs.value + 0.1 * time.delta_seconds()).min(MAX_ECS);
+        if keyboard_input.pressed(settings.controls.right_ecs) || remote.right_ecs_burn > 0.0 {
+            right_ecs.value = (right_ecs.value + ECS_RAMP * time.delta_seconds()).min(max_ecs);
         } else {
             right_ecs.value = 0.0;
         }
     }
 }
 
+fn autopilot_system(
+    autopilot: Res<Autopilot>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    difficulty: Res<ActiveDifficulty>,
+    gravity: Res<PlanetGravity>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    mut _engines: Query<(&mut Thrust, &Altitute, &RadarAltimeter), With<Rocket>>,
+) {
+    // the pilot can always take over by holding the throttle
+    if !autopilot.enabled || keyboard_input.pressed(settings.controls.throttle) {
+        return;
+    }
+
+    let Ok((transform, velocity)) = body.get_single() else {
+        return;
+    };
+    let vertical_speed = velocity.linvel.dot(gravity.up_at(transform.translation));
+
+    for (mut thrust, altitude, radar) in _engines.iter_mut() {
+        // descend quickly while high, slow down close to the ground
+        let height = radar.height.unwrap_or(altitude.value);
+        let target_vertical_speed = -(0.4 + height * 0.1).min(2.5);
+        let hover_thrust = ROCKET_MASS * gravity.surface_gravity;
+        let correction = (target_vertical_speed - vertical_speed) * AUTOPILOT_GAIN;
+        thrust.value = (hover_thrust + correction).clamp(0.0, difficulty.0.max_thrust);
+    }
+}
+
+fn mission_clock_system(time: Res<Time>, mut mission_time: ResMut<MissionTime>) {
+    mission_time.seconds += time.delta_seconds();
+}
+
+/// Touchdowns are reported with the speed from the frame before contact,
+/// since by the time the collision event is read the solver has already
+/// slowed the rocket. Leaving the ground only counts as a liftoff once the
+/// rocket has stayed off it for `LIFTOFF_DEBOUNCE`, so a bounce is a single
+/// touchdown.
+fn flight_event_system(
+    time: Res<Time>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut flight_events: EventWriter<FlightEvent>,
+    mut contacts: Local<usize>,
+    mut left_ground_at: Local<Option<f32>>,
+    mut previous_linvel: Local<Vec3>,
+    mut had_fuel: Local<bool>,
+    body: Query<(Entity, &BodyVelocity), With<RocketCollider>>,
+    fuel: Query<&Fuel, With<Rocket>>,
+) {
+    let Ok((rocket_entity, velocity)) = body.get_single() else {
+        return;
+    };
+
+    for collision_event in collision_events.read() {
+        match collision_event {
+            CollisionEvent::Started(entity1, entity2, _)
+                if *entity1 == rocket_entity || *entity2 == rocket_entity =>
+            {
+                if *contacts == 0 && left_ground_at.take().is_none() {
+                    flight_events.send(FlightEvent::Touchdown {
+                        speed: previous_linvel.length(),
+                    });
+                }
+                *contacts += 1;
+            }
+            CollisionEvent::Stopped(entity1, entity2, _)
+                if *entity1 == rocket_entity || *entity2 == rocket_entity =>
+            {
+                *contacts = contacts.saturating_sub(1);
+                if *contacts == 0 {
+                    *left_ground_at = Some(time.elapsed_seconds());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if left_ground_at.is_some_and(|since| time.elapsed_seconds() - since >= LIFTOFF_DEBOUNCE) {
+        *left_ground_at = None;
+        flight_events.send(FlightEvent::Liftoff);
+    }
+    *previous_linvel = velocity.linvel;
+
+    let has_fuel = fuel.single().value > 0.0;
+    if *had_fuel && !has_fuel {
+        flight_events.send(FlightEvent::Flameout);
+    }
+    *had_fuel = has_fuel;
+}
+
+fn ground_command_system(
+    mut ground_commands: EventReader<GroundCommand>,
+    mut remote: ResMut<RemoteControl>,
+    mut autopilot: ResMut<Autopilot>,
+) {
+    for command in ground_commands.read() {
+        match command {
+            GroundCommand::SetThrottle { value } => remote.throttle = Some(*value),
+            GroundCommand::FireEcs { side, duration } => match side {
+                EcsSide::Left => remote.left_ecs_burn = *duration,
+                EcsSide::Right => remote.right_ecs_burn = *duration,
+            },
+            GroundCommand::ReleaseControls => *remote = RemoteControl::default(),
+            GroundCommand::SetAutopilot { enabled } => autopilot.enabled = *enabled,
+            _ => {}
+        }
+    }
+}
+
+/// Puts the rocket back at the mission's start, on launch and on reset.
+fn apply_mission_system(
+    mut mission_started: EventReader<MissionStarted>,
+    mission: Res<ActiveMission>,
+    settings: Res<Settings>,
+    mut remote: ResMut<RemoteControl>,
+    mut autopilot: ResMut<Autopilot>,
+    mut mission_time: ResMut<MissionTime>,
+    mut rocket: Query<
+        (
+            &mut Transform,
+            &mut Thrust,
+            &mut Fuel,
+            &mut LeftEcs,
+            &mut RightEcs,
+            &mut Velocity,
+        ),
+        With<Rocket>,
+    >,
+    mut collider: Query<
+        (&mut Transform, &mut BodyVelocity, &mut ExternalForce),
+        (With<RocketCollider>, Without<Rocket>),
+    >,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+
+    let start_state = mission.0.start;
+    *remote = RemoteControl::default();
+    autopilot.enabled = settings.assists.auto_throttle;
+    mission_time.seconds = 0.0;
+    let start = Transform::from_translation(start_state.position)
+        .with_rotation(Quat::from_rotation_z(start_state.tilt_degrees.to_radians()));
+
+    for (mut transform, mut thrust, mut fuel, mut left_ecs, mut right_ecs, mut velocity) in
+        rocket.iter_mut()
+    {
+        transform.translation = start.translation;
+        transform.rotation = start.rotation;
+        thrust.value = 0.0;
+        fuel.value = start_state.fuel;
+        left_ecs.value = 0.0;
+        right_ecs.value = 0.0;
+        velocity.value = start_state.velocity;
+    }
+
+    for (mut transform, mut body_velocity, mut ext_force) in collider.iter_mut() {
+        *transform = start;
+        *body_velocity = BodyVelocity::linear(start_state.velocity);
+        *ext_force = ExternalForce::default();
+    }
+}
+
+/// Engines, thrusters and wind. Gravity comes from `GravityPlugin`.
 fn applied_physics_forces_system(
     mut ext_forces: Query<&mut ExternalForce, With<RocketCollider>>,
+    fuel: Query<&Fuel, With<Rocket>>,
     mut _thrust: Query<&mut Thrust, With<Rocket>>,
     mut _left_ecs: Query<&mut LeftEcs, With<Rocket>>,
     mut _right_ecs: Query<&mut RightEcs, With<Rocket>>,
@@ -306,18 +473,22 @@ fn applied_physics_forces_system(
 ) {
     const LOCAL_UP: Vec3 = Vec3::Y;
 
+    // engines and thrusters flame out once the tanks are dry
+    let fuel_flow = if fuel.single().value > 0.0 { 1.0 } else { 0.0 };
+
     for mut ext_force in ext_forces.iter_mut() {
         let rotation = _rocket_transform.single_mut().rotation;
         let thrust_direction = rotation.mul_vec3(LOCAL_UP);
         let right_ecs = rotation.mul_vec3(-Vec3::Z) * _right_ecs.single_mut().value;
         let left_ecs = rotation.mul_vec3(Vec3::Z) * _left_ecs.single_mut().value;
         //tilt from left and right ecs
-        let tilt = right_ecs + left_ecs;
+        let tilt = (right_ecs + left_ecs) * fuel_flow;
         ext_force.torque = tilt * 0.1;
 
+        ext_force.force = thrust_direction * _thrust.single_mut().value * fuel_flow;
+
         for (wind_direction, wind_speed) in _weather.iter_mut() {
-            let wind = wind_direction.value * wind_speed.value;
-            ext_force.force = thrust_direction * _thrust.single_mut().value + wind;
+            ext_force.force += wind_direction.value * wind_speed.value;
         }
     }
 }
@@ -0,0 +1,180 @@
+use bevy::{log, prelude::*};
+use bevy_rapier3d::dynamics::Velocity as BodyVelocity;
+
+use super::{
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    mission::MissionStarted,
+    rocket::{
+        keyboard_control_system, LeftEcs, RemoteControl, RightEcs, Rocket, RocketCollider, ECS_RAMP,
+    },
+    settings::Settings,
+    splash::GameState,
+};
+
+// thruster burn per rad/s of roll left to cancel
+const RATE_GAIN: f32 = 4.0;
+// roll rate asked for per radian off the held attitude
+const ATTITUDE_GAIN: f32 = 1.5;
+const MAX_CORRECTION_RATE: f32 = 1.0;
+const RATE_DEADBAND: f32 = 0.02;
+
+/// What the stability augmentation system is doing, cycled with T by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SasMode {
+    #[default]
+    Off,
+    /// Cancels any roll rate, wherever the rocket is pointing.
+    KillRotation,
+    /// Holds the attitude the rocket had when selected or last steered.
+    HoldAttitude,
+    /// Turns the rocket upright against local gravity and holds it there.
+    Level,
+}
+
+impl SasMode {
+    fn next(self) -> Self {
+        match self {
+            SasMode::Off => SasMode::KillRotation,
+            SasMode::KillRotation => SasMode::HoldAttitude,
+            SasMode::HoldAttitude => SasMode::Level,
+            SasMode::Level => SasMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SasMode::Off => "SAS OFF",
+            SasMode::KillRotation => "SAS STAB",
+            SasMode::HoldAttitude => "SAS HOLD",
+            SasMode::Level => "SAS LEVEL",
+        }
+    }
+}
+
+/// Fires the ECS thrusters to steady the rocket, burning their fuel.
+#[derive(Resource, Default)]
+pub struct Sas {
+    pub mode: SasMode,
+    /// Attitude kept in `HoldAttitude`.
+    target: Quat,
+    /// Thruster burn, positive on the left one. It builds up like a held
+    /// key so the assist has no more authority than the pilot.
+    burn: f32,
+}
+
+pub struct SasPlugin;
+
+impl Plugin for SasPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sas>()
+            .add_systems(Update, engage_on_start_system)
+            .add_systems(
+                Update,
+                (switch_sas_mode_system, sas_system)
+                    .chain()
+                    .after(keyboard_control_system)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// The stability assist setting decides whether missions start with SAS on.
+fn engage_on_start_system(
+    mut mission_started: EventReader<MissionStarted>,
+    settings: Res<Settings>,
+    mut sas: ResMut<Sas>,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+
+    sas.mode = if settings.assists.sas {
+        SasMode::KillRotation
+    } else {
+        SasMode::Off
+    };
+}
+
+fn switch_sas_mode_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut sas: ResMut<Sas>,
+    body: Query<&Transform, With<RocketCollider>>,
+) {
+    if !keyboard_input.just_pressed(settings.controls.sas) {
+        return;
+    }
+
+    sas.mode = sas.mode.next();
+    if let Ok(transform) = body.get_single() {
+        sas.target = transform.rotation;
+    }
+    log::info!("SAS mode: {:?}", sas.mode);
+}
+
+pub fn sas_system(
+    time: Res<Time>,
+    mut sas: ResMut<Sas>,
+    settings: Res<Settings>,
+    difficulty: Res<ActiveDifficulty>,
+    gravity: Res<PlanetGravity>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    remote: Res<RemoteControl>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    mut _engines: Query<(&mut LeftEcs, &mut RightEcs), With<Rocket>>,
+) {
+    if sas.mode == SasMode::Off {
+        sas.burn = 0.0;
+        return;
+    }
+    let Ok((transform, velocity)) = body.get_single() else {
+        return;
+    };
+
+    // the pilot always wins, and a held attitude follows their steering
+    let controls = &settings.controls;
+    if keyboard_input.any_pressed([controls.left_ecs, controls.right_ecs])
+        || remote.left_ecs_burn > 0.0
+        || remote.right_ecs_burn > 0.0
+    {
+        sas.target = transform.rotation;
+        sas.burn = 0.0;
+        return;
+    }
+
+    // the thrusters only turn the rocket about its local Z: the left one
+    // spins it positively, the right one negatively
+    let axis = transform.rotation * Vec3::Z;
+    let roll_rate = velocity.angvel.dot(axis);
+    let target_up = match sas.mode {
+        SasMode::Off | SasMode::KillRotation => None,
+        SasMode::HoldAttitude => Some(sas.target * Vec3::Y),
+        SasMode::Level => Some(gravity.up_at(transform.translation)),
+    };
+    let wanted_rate = target_up.map_or(0.0, |target_up| {
+        let up = transform.rotation * Vec3::Y;
+        let error = up.cross(target_up).dot(axis).atan2(up.dot(target_up));
+        (error * ATTITUDE_GAIN).clamp(-MAX_CORRECTION_RATE, MAX_CORRECTION_RATE)
+    });
+
+    let rate_error = wanted_rate - roll_rate;
+    let wanted_burn = if rate_error.abs() < RATE_DEADBAND {
+        0.0
+    } else {
+        (rate_error * RATE_GAIN).clamp(-difficulty.0.max_ecs, difficulty.0.max_ecs)
+    };
+
+    // like a key: builds up while the same side is wanted, cut when it isn't
+    let held = if wanted_burn * sas.burn > 0.0 {
+        sas.burn.abs()
+    } else {
+        0.0
+    };
+    let magnitude = (held + ECS_RAMP * time.delta_seconds()).min(wanted_burn.abs());
+    sas.burn = magnitude.copysign(wanted_burn);
+
+    for (mut left_ecs, mut right_ecs) in _engines.iter_mut() {
+        (left_ecs.value, right_ecs.value) = (sas.burn.max(0.0), (-sas.burn).max(0.0));
+    }
+}
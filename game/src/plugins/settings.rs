@@ -0,0 +1,891 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use bevy::{asset::io::file::FileAssetReader, log, prelude::*};
+use serde_derive::{Deserialize, Serialize};
+
+use super::{
+    camera::CameraRig,
+    difficulty::{Assists, Difficulty},
+    exhaust::ExhaustSettings,
+    ground_control::{GROUND_CONTROL_ADDRESS, MAX_TELEMETRY_RATE},
+    menu::OpenSettings,
+    splash::{GameState, Title},
+    telemetry::{Backpressure, TelemetrySettings, TELEMETRY_ADDRESS},
+};
+
+/// Settings are read from here, relative to the project root, unless
+/// `--config` or `RED_HORIZON_CONFIG` point elsewhere.
+pub const SETTINGS_FILE: &str = "settings.json";
+pub const CONFIG_ENV: &str = "RED_HORIZON_CONFIG";
+/// Set to a number to land on a procedurally generated site with that seed.
+pub const TERRAIN_SEED_ENV: &str = "RED_HORIZON_TERRAIN_SEED";
+/// Set to an address such as `127.0.0.1:9100` to serve `/metrics`.
+pub const METRICS_ADDRESS_ENV: &str = "RED_HORIZON_METRICS_ADDR";
+
+const USAGE: &str = "Usage: game [options]
+
+  --config <path>           settings file to load and save
+  --difficulty <level>      easy, normal or hard
+  --assists <list>          none, all or any of sas,prediction,auto_throttle
+  --quality <level>         low, medium or high
+  --fov <degrees>           camera field of view, 30 to 110
+  --particles <count>       exhaust particle budget
+  --stars <count>           number of stars in the sky
+  --volume <0..1>           engine volume
+  --seed <number>           fly every mission over procedural terrain
+  --telemetry <address>     telemetry listen address
+  --telemetry-rate <hz>     telemetry samples per second, up to 120
+  --backpressure <mode>     drop_oldest, latest_only or block, for clients
+                            that don't pick their own
+  --ground-control <addr>   ground control listen address
+  --metrics <address>       serve /metrics on this address
+  --help                    print this message";
+
+const MIN_FOV: f32 = 30.0;
+const MAX_FOV: f32 = 110.0;
+const MAX_PARTICLES: usize = 3000;
+const MAX_STARS: usize = 5000;
+// seconds a rebind waits for a key before giving up, so any key, Escape
+// included, can be bound
+const CAPTURE_TIMEOUT: f32 = 5.0;
+
+/// Named fog colours offered on the settings screen.
+const FOG_PRESETS: &[(&str, [f32; 3])] = &[
+    ("Purple", [0.5, 0.0, 0.5]),
+    ("Dust", [0.89, 0.51, 0.27]),
+    ("Haze", [0.55, 0.45, 0.45]),
+    ("Black", [0.0, 0.0, 0.0]),
+];
+
+/// Keys that can be bound, by the name used in the settings file.
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Backspace,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| key_name(*key) == name)
+}
+
+/// (De)serializes a `KeyCode` by its name, e.g. `"KeyA"` or `"Space"`.
+mod key_code {
+    use bevy::prelude::KeyCode;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::key_name(*key))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+        let name: String = serde::Deserialize::deserialize(deserializer)?;
+        super::parse_key(&name).ok_or_else(|| D::Error::custom(format!("unknown key {}", name)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphicsQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl GraphicsQuality {
+    pub fn msaa(self) -> Msaa {
+        match self {
+            GraphicsQuality::Low => Msaa::Off,
+            GraphicsQuality::Medium => Msaa::Sample2,
+            GraphicsQuality::High => Msaa::Sample4,
+        }
+    }
+
+    pub fn shadows(self) -> bool {
+        self != GraphicsQuality::Low
+    }
+
+    fn next(self) -> Self {
+        match self {
+            GraphicsQuality::Low => GraphicsQuality::Medium,
+            GraphicsQuality::Medium => GraphicsQuality::High,
+            GraphicsQuality::High => GraphicsQuality::Low,
+        }
+    }
+}
+
+impl FromStr for GraphicsQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(GraphicsQuality::Low),
+            "medium" => Ok(GraphicsQuality::Medium),
+            "high" => Ok(GraphicsQuality::High),
+            _ => Err(format!("unknown graphics quality: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub quality: GraphicsQuality,
+    pub fov_degrees: f32,
+    /// Maximum number of exhaust particles alive at once.
+    pub particle_budget: usize,
+    pub star_count: usize,
+    /// sRGB.
+    pub fog_color: [f32; 3],
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            quality: GraphicsQuality::default(),
+            fov_degrees: 55.0,
+            particle_budget: 600,
+            star_count: 1500,
+            fog_color: FOG_PRESETS[0].1,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    fn clamp_to_limits(&mut self) {
+        self.fov_degrees = if self.fov_degrees.is_finite() {
+            self.fov_degrees.clamp(MIN_FOV, MAX_FOV)
+        } else {
+            GraphicsSettings::default().fov_degrees
+        };
+        self.particle_budget = self.particle_budget.min(MAX_PARTICLES);
+        self.star_count = self.star_count.min(MAX_STARS);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct KeyBindings {
+    #[serde(with = "key_code")]
+    pub throttle: KeyCode,
+    #[serde(with = "key_code")]
+    pub left_ecs: KeyCode,
+    #[serde(with = "key_code")]
+    pub right_ecs: KeyCode,
+    #[serde(with = "key_code")]
+    pub pause: KeyCode,
+    #[serde(with = "key_code")]
+    pub camera: KeyCode,
+    #[serde(with = "key_code")]
+    pub hud: KeyCode,
+    #[serde(with = "key_code")]
+    pub target_pad: KeyCode,
+    #[serde(with = "key_code")]
+    pub sas: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            throttle: KeyCode::Space,
+            left_ecs: KeyCode::KeyA,
+            right_ecs: KeyCode::KeyD,
+            pause: KeyCode::Escape,
+            camera: KeyCode::KeyC,
+            hud: KeyCode::KeyH,
+            target_pad: KeyCode::Tab,
+            sas: KeyCode::KeyT,
+        }
+    }
+}
+
+/// Listen addresses and the telemetry stream, only read at startup.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub telemetry_address: String,
+    /// Until a ground station sets another.
+    pub telemetry_rate_hz: f32,
+    /// For clients that don't pick their own.
+    pub telemetry_backpressure: Backpressure,
+    pub ground_control_address: String,
+    pub metrics_address: Option<String>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            telemetry_address: TELEMETRY_ADDRESS.to_string(),
+            telemetry_rate_hz: TelemetrySettings::default().rate_hz,
+            telemetry_backpressure: Backpressure::default(),
+            ground_control_address: GROUND_CONTROL_ADDRESS.to_string(),
+            metrics_address: None,
+        }
+    }
+}
+
+/// Everything the player can change, persisted as JSON.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub graphics: GraphicsSettings,
+    /// 0 to 1.
+    pub volume: f32,
+    pub controls: KeyBindings,
+    pub difficulty: Difficulty,
+    /// Starts out as the difficulty's assists, then toggled one by one.
+    pub assists: Assists,
+    pub network: NetworkSettings,
+    /// Flies every mission over procedural terrain with this seed.
+    pub terrain_seed: Option<u64>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            graphics: GraphicsSettings::default(),
+            volume: 1.0,
+            controls: KeyBindings::default(),
+            difficulty: Difficulty::default(),
+            assists: Difficulty::default().preset().assists,
+            network: NetworkSettings::default(),
+            terrain_seed: None,
+            path: FileAssetReader::get_base_path().join(SETTINGS_FILE),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, then applies environment variables and
+    /// command line options on top. Exits on invalid options.
+    pub fn load() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+
+        let path = option_value(&args, "--config")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var(CONFIG_ENV).ok().map(PathBuf::from))
+            .unwrap_or_else(|| Settings::default().path);
+
+        // logging isn't set up yet, so problems go straight to stderr
+        let mut settings = if path.exists() {
+            Self::read(&path).unwrap_or_else(|e| {
+                eprintln!("Ignoring settings in {:?}: {}", path, e);
+                Settings::default()
+            })
+        } else {
+            Settings::default()
+        };
+        settings.path = path;
+        settings.apply_env();
+
+        if let Err(e) = settings.apply_args(&args) {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+        settings
+    }
+
+    /// Values out of range, e.g. from a hand-edited file, are clamped to
+    /// what the settings screen allows.
+    fn read(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut settings: Settings = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        settings.graphics.clamp_to_limits();
+        settings.volume = if settings.volume.is_finite() {
+            settings.volume.clamp(0.0, 1.0)
+        } else {
+            Settings::default().volume
+        };
+        if !valid_telemetry_rate(settings.network.telemetry_rate_hz) {
+            settings.network.telemetry_rate_hz = NetworkSettings::default().telemetry_rate_hz;
+        }
+        Ok(settings)
+    }
+
+    /// Writes the settings screen's values back to the file, keeping whatever
+    /// else is in there rather than persisting one-off overrides.
+    pub fn save(&self) -> Result<(), String> {
+        let mut stored = if self.path.exists() {
+            Self::read(&self.path)?
+        } else {
+            Settings::default()
+        };
+        stored.graphics = self.graphics.clone();
+        stored.volume = self.volume;
+        stored.controls = self.controls;
+        stored.difficulty = self.difficulty;
+        stored.assists = self.assists;
+
+        let contents = serde_json::to_string_pretty(&stored).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn apply_env(&mut self) {
+        if let Some(seed) = std::env::var(TERRAIN_SEED_ENV)
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+        {
+            self.terrain_seed = Some(seed);
+        }
+        if let Ok(address) = std::env::var(METRICS_ADDRESS_ENV) {
+            self.network.metrics_address = Some(address);
+        }
+    }
+
+    fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        // explicit assists win over the difficulty's, whatever the order
+        let mut assists = None;
+        let mut args = args.iter();
+        while let Some(option) = args.next() {
+            let mut value = || {
+                args.next()
+                    .map(String::as_str)
+                    .ok_or_else(|| format!("{} needs a value", option))
+            };
+
+            match option.as_str() {
+                "--config" => {
+                    value()?;
+                }
+                "--difficulty" => {
+                    self.difficulty = value()?.parse()?;
+                    self.assists = self.difficulty.preset().assists;
+                }
+                "--assists" => assists = Some(value()?.parse()?),
+                "--quality" => self.graphics.quality = value()?.parse()?,
+                "--fov" => {
+                    let fov: f32 = parse_number(option, value()?)?;
+                    if !(MIN_FOV..=MAX_FOV).contains(&fov) {
+                        return Err(format!(
+                            "--fov must be between {} and {}, got {}",
+                            MIN_FOV, MAX_FOV, fov
+                        ));
+                    }
+                    self.graphics.fov_degrees = fov;
+                }
+                "--particles" => {
+                    let budget: usize = parse_number(option, value()?)?;
+                    if budget > MAX_PARTICLES {
+                        return Err(format!(
+                            "--particles must be at most {}, got {}",
+                            MAX_PARTICLES, budget
+                        ));
+                    }
+                    self.graphics.particle_budget = budget;
+                }
+                "--stars" => {
+                    let count: usize = parse_number(option, value()?)?;
+                    if count > MAX_STARS {
+                        return Err(format!(
+                            "--stars must be at most {}, got {}",
+                            MAX_STARS, count
+                        ));
+                    }
+                    self.graphics.star_count = count;
+                }
+                "--volume" => {
+                    let volume: f32 = parse_number(option, value()?)?;
+                    if !(0.0..=1.0).contains(&volume) {
+                        return Err(format!("--volume must be between 0 and 1, got {}", volume));
+                    }
+                    self.volume = volume;
+                }
+                "--seed" => self.terrain_seed = Some(parse_number(option, value()?)?),
+                "--telemetry" => self.network.telemetry_address = value()?.to_string(),
+                "--telemetry-rate" => {
+                    let hz: f32 = parse_number(option, value()?)?;
+                    if !valid_telemetry_rate(hz) {
+                        return Err(format!(
+                            "--telemetry-rate must be above 0 and at most {}, got {}",
+                            MAX_TELEMETRY_RATE, hz
+                        ));
+                    }
+                    self.network.telemetry_rate_hz = hz;
+                }
+                "--backpressure" => self.network.telemetry_backpressure = value()?.parse()?,
+                "--ground-control" => self.network.ground_control_address = value()?.to_string(),
+                "--metrics" => self.network.metrics_address = Some(value()?.to_string()),
+                _ => return Err(format!("unknown option: {}", option)),
+            }
+        }
+
+        if let Some(assists) = assists {
+            self.assists = assists;
+        }
+        Ok(())
+    }
+}
+
+/// Same range a ground station may set.
+fn valid_telemetry_rate(hz: f32) -> bool {
+    hz > 0.0 && hz <= MAX_TELEMETRY_RATE
+}
+
+fn on_off(enabled: bool) -> String {
+    if enabled { "On" } else { "Off" }.to_string()
+}
+
+fn option_value<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == option)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+fn parse_number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", option, value))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binding {
+    Throttle,
+    LeftEcs,
+    RightEcs,
+    Pause,
+    Camera,
+    Hud,
+    TargetPad,
+    Sas,
+}
+
+impl Binding {
+    const ALL: [Binding; 8] = [
+        Binding::Throttle,
+        Binding::LeftEcs,
+        Binding::RightEcs,
+        Binding::Pause,
+        Binding::Camera,
+        Binding::Hud,
+        Binding::TargetPad,
+        Binding::Sas,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Binding::Throttle => "Throttle",
+            Binding::LeftEcs => "Left thruster",
+            Binding::RightEcs => "Right thruster",
+            Binding::Pause => "Pause",
+            Binding::Camera => "Camera",
+            Binding::Hud => "HUD",
+            Binding::TargetPad => "Next pad",
+            Binding::Sas => "SAS mode",
+        }
+    }
+
+    fn key_mut(self, controls: &mut KeyBindings) -> &mut KeyCode {
+        match self {
+            Binding::Throttle => &mut controls.throttle,
+            Binding::LeftEcs => &mut controls.left_ecs,
+            Binding::RightEcs => &mut controls.right_ecs,
+            Binding::Pause => &mut controls.pause,
+            Binding::Camera => &mut controls.camera,
+            Binding::Hud => &mut controls.hud,
+            Binding::TargetPad => &mut controls.target_pad,
+            Binding::Sas => &mut controls.sas,
+        }
+    }
+}
+
+/// One line of the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsRow {
+    Quality,
+    Fov,
+    Particles,
+    Stars,
+    FogColor,
+    Volume,
+    Difficulty,
+    Sas,
+    TrajectoryPrediction,
+    AutoThrottle,
+    Key(Binding),
+}
+
+impl SettingsRow {
+    fn all() -> Vec<SettingsRow> {
+        let mut rows = vec![
+            SettingsRow::Quality,
+            SettingsRow::Fov,
+            SettingsRow::Particles,
+            SettingsRow::Stars,
+            SettingsRow::FogColor,
+            SettingsRow::Volume,
+            SettingsRow::Difficulty,
+            SettingsRow::Sas,
+            SettingsRow::TrajectoryPrediction,
+            SettingsRow::AutoThrottle,
+        ];
+        rows.extend(Binding::ALL.map(SettingsRow::Key));
+        rows
+    }
+
+    fn describe(self, settings: &Settings) -> (String, String) {
+        let graphics = &settings.graphics;
+        match self {
+            SettingsRow::Quality => ("Graphics".into(), format!("{:?}", graphics.quality)),
+            SettingsRow::Fov => (
+                "Field of view".into(),
+                format!("{:.0} deg", graphics.fov_degrees),
+            ),
+            SettingsRow::Particles => ("Particles".into(), graphics.particle_budget.to_string()),
+            SettingsRow::Stars => ("Stars".into(), graphics.star_count.to_string()),
+            SettingsRow::FogColor => (
+                "Fog".into(),
+                FOG_PRESETS
+                    .iter()
+                    .find(|(_, color)| *color == graphics.fog_color)
+                    .map_or("Custom", |(name, _)| *name)
+                    .to_string(),
+            ),
+            SettingsRow::Volume => ("Volume".into(), format!("{:.0}%", settings.volume * 100.0)),
+            SettingsRow::Difficulty => ("Difficulty".into(), format!("{:?}", settings.difficulty)),
+            SettingsRow::Sas => ("SAS at launch".into(), on_off(settings.assists.sas)),
+            SettingsRow::TrajectoryPrediction => (
+                "Impact prediction".into(),
+                on_off(settings.assists.trajectory_prediction),
+            ),
+            SettingsRow::AutoThrottle => (
+                "Auto-throttle".into(),
+                on_off(settings.assists.auto_throttle),
+            ),
+            SettingsRow::Key(binding) => {
+                let mut controls = settings.controls;
+                (
+                    binding.label().into(),
+                    key_name(*binding.key_mut(&mut controls)),
+                )
+            }
+        }
+    }
+
+    /// Steps the value left (-1) or right (+1).
+    fn adjust(self, settings: &mut Settings, step: i32) {
+        let graphics = &mut settings.graphics;
+        match self {
+            SettingsRow::Quality => {
+                graphics.quality = graphics.quality.next();
+                if step < 0 {
+                    graphics.quality = graphics.quality.next();
+                }
+            }
+            SettingsRow::Fov => {
+                graphics.fov_degrees =
+                    (graphics.fov_degrees + 5.0 * step as f32).clamp(MIN_FOV, MAX_FOV);
+            }
+            SettingsRow::Particles => {
+                graphics.particle_budget = graphics
+                    .particle_budget
+                    .saturating_add_signed(100 * step as isize)
+                    .min(MAX_PARTICLES);
+            }
+            SettingsRow::Stars => {
+                graphics.star_count = graphics
+                    .star_count
+                    .saturating_add_signed(250 * step as isize)
+                    .min(MAX_STARS);
+            }
+            SettingsRow::FogColor => {
+                let current = FOG_PRESETS
+                    .iter()
+                    .position(|(_, color)| *color == graphics.fog_color)
+                    .unwrap_or(0);
+                let next = (current as i32 + step).rem_euclid(FOG_PRESETS.len() as i32);
+                graphics.fog_color = FOG_PRESETS[next as usize].1;
+            }
+            SettingsRow::Volume => {
+                settings.volume = ((settings.volume + 0.1 * step as f32) * 10.0).round() / 10.0;
+                settings.volume = settings.volume.clamp(0.0, 1.0);
+            }
+            SettingsRow::Difficulty => {
+                settings.difficulty = settings.difficulty.next();
+                if step < 0 {
+                    settings.difficulty = settings.difficulty.next();
+                }
+                settings.assists = settings.difficulty.preset().assists;
+            }
+            SettingsRow::Sas => settings.assists.sas = !settings.assists.sas,
+            SettingsRow::TrajectoryPrediction => {
+                settings.assists.trajectory_prediction = !settings.assists.trajectory_prediction
+            }
+            SettingsRow::AutoThrottle => {
+                settings.assists.auto_throttle = !settings.assists.auto_throttle
+            }
+            SettingsRow::Key(_) => {}
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct SettingsScreen {
+    selected: usize,
+    /// Waiting for a key to bind to the selected row.
+    capturing: bool,
+    /// Seconds left before capture is cancelled.
+    capture_left: f32,
+    /// Where to go back to when the screen closes.
+    previous: Option<GameState>,
+}
+
+#[derive(Component)]
+struct SettingsList;
+
+pub struct SettingsPlugin {
+    pub settings: Settings,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<SettingsScreen>()
+            .add_systems(
+                Update,
+                (
+                    open_settings_system,
+                    apply_settings_system.run_if(resource_changed::<Settings>),
+                ),
+            )
+            .add_systems(OnEnter(GameState::Settings), show_settings)
+            .add_systems(
+                Update,
+                settings_screen_system.run_if(in_state(GameState::Settings)),
+            );
+    }
+}
+
+fn open_settings_system(
+    mut open_settings: EventReader<OpenSettings>,
+    mut screen: ResMut<SettingsScreen>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if open_settings.read().count() == 0 || *state.get() == GameState::Settings {
+        return;
+    }
+
+    screen.selected = 0;
+    screen.capturing = false;
+    screen.previous = Some(*state.get());
+    next_state.set(GameState::Settings);
+}
+
+fn apply_settings_system(
+    settings: Res<Settings>,
+    mut msaa: ResMut<Msaa>,
+    mut exhaust: ResMut<ExhaustSettings>,
+    mut cameras: Query<&mut Projection, With<CameraRig>>,
+) {
+    let graphics = &settings.graphics;
+    *msaa = graphics.quality.msaa();
+    exhaust.budget = graphics.particle_budget;
+
+    for mut projection in cameras.iter_mut() {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = graphics.fov_degrees.to_radians();
+        }
+    }
+}
+
+fn show_settings(mut commands: Commands, settings: Res<Settings>, screen: Res<SettingsScreen>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Settings",
+            TextStyle {
+                font_size: 64.,
+                color: Color::srgb(0.8, 0.2, 0.2),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(8.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        Title,
+        StateScoped(GameState::Settings),
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            settings_text(&settings, &screen),
+            TextStyle {
+                font_size: 24.,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(22.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        SettingsList,
+        StateScoped(GameState::Settings),
+    ));
+}
+
+fn settings_text(settings: &Settings, screen: &SettingsScreen) -> String {
+    let mut text: String = SettingsRow::all()
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let (label, value) = row.describe(settings);
+            if index != screen.selected {
+                format!("{}: {}\n", label, value)
+            } else if screen.capturing {
+                format!(
+                    "> {}: press a key, or wait {:.0}s to cancel <\n",
+                    label,
+                    screen.capture_left.ceil()
+                )
+            } else {
+                format!("> {}: {} <\n", label, value)
+            }
+        })
+        .collect();
+
+    text.push_str(&format!(
+        "\nUp/Down to choose, Left/Right to change, Enter to rebind, Escape to save and go back\n\
+         Telemetry on {}, ground control on {}, set in {}",
+        settings.network.telemetry_address,
+        settings.network.ground_control_address,
+        settings.path.display()
+    ));
+    text
+}
+
+fn settings_screen_system(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut screen: ResMut<SettingsScreen>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut list_query: Query<&mut Text, With<SettingsList>>,
+) {
+    let rows = SettingsRow::all();
+    let row = rows[screen.selected];
+
+    if screen.capturing {
+        let SettingsRow::Key(binding) = row else {
+            screen.capturing = false;
+            return;
+        };
+        if let Some(key) = keyboard_input
+            .get_just_pressed()
+            .copied()
+            .find(|key| BINDABLE_KEYS.contains(key))
+        {
+            // a key already in use swaps over to the old binding
+            let controls = &mut settings.controls;
+            let previous = *binding.key_mut(controls);
+            for other in Binding::ALL {
+                if *other.key_mut(controls) == key {
+                    *other.key_mut(controls) = previous;
+                }
+            }
+            *binding.key_mut(controls) = key;
+            screen.capturing = false;
+        } else {
+            let left = screen.capture_left - time.delta_seconds();
+            // only touch the screen when the countdown shown changes
+            if left.ceil() != screen.capture_left.ceil() {
+                screen.capturing = left > 0.0;
+            }
+            screen.bypass_change_detection().capture_left = left;
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        match settings.save() {
+            Ok(()) => log::info!("Saved settings to {:?}", settings.path()),
+            Err(e) => log::error!("Failed to save settings to {:?}: {}", settings.path(), e),
+        }
+        next_state.set(screen.previous.unwrap_or(GameState::MainMenu));
+        return;
+    } else if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        screen.selected = (screen.selected + 1) % rows.len();
+    } else if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        screen.selected = (screen.selected + rows.len() - 1) % rows.len();
+    } else if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        row.adjust(&mut settings, 1);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        row.adjust(&mut settings, -1);
+    } else if keyboard_input.just_pressed(KeyCode::Enter) {
+        screen.capturing = matches!(row, SettingsRow::Key(_));
+        screen.capture_left = CAPTURE_TIMEOUT;
+    }
+
+    if screen.is_changed() || settings.is_changed() {
+        for mut text in list_query.iter_mut() {
+            text.sections[0].value = settings_text(&settings, &screen);
+        }
+    }
+}
@@ -1,10 +1,56 @@
 use bevy::{log, prelude::*};
+use serde_derive::Serialize;
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+use super::{
+    difficulty::ActiveDifficulty,
+    menu::OpenSettings,
+    mission::{ActiveMission, MissionCatalog, MissionStarted, SelectedMission},
+    settings::Settings,
+};
+
+// how long the title card stays up before the main menu
+const SPLASH_DURATION: f32 = 2.5;
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States, Serialize)]
 pub enum GameState {
+    /// Title card shown on launch.
     #[default]
-    Paused,
+    Splash,
+    /// Mission selection.
+    MainMenu,
+    /// Details of the selected mission before launching.
+    Briefing,
     Playing,
+    Paused,
+    /// Touched down within the mission's limits, the rocket is settling.
+    Landed,
+    /// Touched down outside the mission's limits.
+    Crashed,
+    /// Score summary after a landing or crash.
+    Results,
+    /// Options screen, opened from the main or pause menu.
+    Settings,
+}
+
+impl GameState {
+    /// States in which the rocket is simulated.
+    pub fn is_flying(&self) -> bool {
+        matches!(
+            self,
+            GameState::Playing | GameState::Landed | GameState::Crashed
+        )
+    }
+
+    /// States a ground station may reset the mission from. Menus are left
+    /// alone so the briefing and settings screens aren't skipped.
+    pub fn accepts_reset(&self) -> bool {
+        self.is_flying() || *self == GameState::Paused
+    }
+}
+
+/// Run condition for systems that keep going after touchdown.
+pub fn in_flight(state: Res<State<GameState>>) -> bool {
+    state.get().is_flying()
 }
 
 pub struct SplashPlugin;
@@ -15,36 +61,25 @@ pub struct Title;
 #[derive(Component)]
 pub struct Subtitle;
 
+#[derive(Component)]
+struct MissionList;
+
 impl Plugin for SplashPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
-            .add_systems(Update, pause_system)
-            .add_systems(Startup, show_splash_screen);
-    }
-}
-
-fn pause_system(
-    mut commands: Commands,
-    mut state: ResMut<NextState<GameState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    title_query: Query<Entity, With<Title>>,
-    subtitle_query: Query<Entity, With<Subtitle>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
-        state.set(GameState::Paused)
-    }
-
-    if keyboard_input.just_pressed(KeyCode::Enter) {
-        state.set(GameState::Playing);
-        // desawn the title and subtitle
-        for entity in title_query.iter() {
-            log::info!("Despawning title");
-            commands.entity(entity).despawn();
-        }
-        for entity in subtitle_query.iter() {
-            log::info!("Despawning subtitle");
-            commands.entity(entity).despawn();
-        }
+            .enable_state_scoped_entities::<GameState>()
+            .add_systems(OnEnter(GameState::Splash), show_splash_screen)
+            .add_systems(Update, splash_system.run_if(in_state(GameState::Splash)))
+            .add_systems(OnEnter(GameState::MainMenu), show_main_menu)
+            .add_systems(
+                Update,
+                mission_select_system.run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(OnEnter(GameState::Briefing), show_briefing)
+            .add_systems(
+                Update,
+                briefing_system.run_if(in_state(GameState::Briefing)),
+            );
     }
 }
 
@@ -65,11 +100,12 @@ fn show_splash_screen(mut commands: Commands) {
             ..default()
         }),
         Title,
+        StateScoped(GameState::Splash),
     ));
 
     commands.spawn((
         TextBundle::from_section(
-            "Press Enter to start",
+            "Press any key",
             TextStyle {
                 font_size: 30.,
                 ..default()
@@ -82,5 +118,213 @@ fn show_splash_screen(mut commands: Commands) {
             ..default()
         }),
         Subtitle,
+        StateScoped(GameState::Splash),
+    ));
+}
+
+fn splash_system(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    *elapsed += time.delta_seconds();
+    if *elapsed > SPLASH_DURATION || keyboard_input.get_just_pressed().next().is_some() {
+        state.set(GameState::MainMenu);
+    }
+}
+
+fn show_main_menu(
+    mut commands: Commands,
+    catalog: Res<MissionCatalog>,
+    selected: Res<SelectedMission>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Red Horizon",
+            TextStyle {
+                font_size: 64.,
+                color: Color::srgb(0.8, 0.2, 0.2),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(20.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        Title,
+        StateScoped(GameState::MainMenu),
     ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Choose a mission with the arrow keys and press Enter, S for settings",
+            TextStyle {
+                font_size: 24.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        Subtitle,
+        StateScoped(GameState::MainMenu),
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            mission_list_text(&catalog, selected.0),
+            TextStyle {
+                font_size: 24.,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(50.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        MissionList,
+        StateScoped(GameState::MainMenu),
+    ));
+}
+
+fn mission_select_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    catalog: Res<MissionCatalog>,
+    mut selected: ResMut<SelectedMission>,
+    mut state: ResMut<NextState<GameState>>,
+    mut open_settings: EventWriter<OpenSettings>,
+    mut list_query: Query<&mut Text, With<MissionList>>,
+) {
+    let count = catalog.missions.len();
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        selected.0 = (selected.0 + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        selected.0 = (selected.0 + count - 1) % count;
+    }
+
+    if selected.is_changed() {
+        for mut text in list_query.iter_mut() {
+            text.sections[0].value = mission_list_text(&catalog, selected.0);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        state.set(GameState::Briefing);
+    } else if keyboard_input.just_pressed(KeyCode::KeyS) {
+        open_settings.send(OpenSettings);
+    }
+}
+
+fn mission_list_text(catalog: &MissionCatalog, selected: usize) -> String {
+    catalog
+        .missions
+        .iter()
+        .enumerate()
+        .map(|(index, mission)| {
+            if index == selected {
+                format!("> {} <\n", mission.name)
+            } else {
+                format!("{}\n", mission.name)
+            }
+        })
+        .collect()
+}
+
+fn show_briefing(
+    mut commands: Commands,
+    catalog: Res<MissionCatalog>,
+    selected: Res<SelectedMission>,
+    settings: Res<Settings>,
+) {
+    let preset = settings.difficulty.preset();
+    let mission = &preset.apply(&catalog.missions[selected.0]);
+    let criteria = &mission.success;
+    let wind = match mission.wind {
+        Some(wind) if wind.gust_kph > 0.0 => format!(
+            "{:.0} kph gusting {:.0}",
+            wind.speed_kph * preset.wind_multiplier,
+            (wind.speed_kph + wind.gust_kph) * preset.wind_multiplier
+        ),
+        Some(wind) => format!("{:.0} kph", wind.speed_kph * preset.wind_multiplier),
+        None => "live Mars weather".to_string(),
+    };
+    let pads = mission
+        .pads
+        .iter()
+        .map(|pad| format!("{} (x{:.1})", pad.name, pad.score_multiplier))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                format!("{}\n\n", mission.name),
+                TextStyle {
+                    font_size: 48.,
+                    color: Color::srgb(0.8, 0.2, 0.2),
+                    ..default()
+                },
+            ),
+            TextSection::new(
+                format!(
+                    "{}\n\nDifficulty: {:?}\nPads: {}\nFuel: {:.0}\nWind: {}\nTouch down below {:.1} m/s, tilted less than {:.0} deg{}\n\nEnter to launch, Escape to go back",
+                    mission.description,
+                    settings.difficulty,
+                    pads,
+                    mission.start.fuel,
+                    wind,
+                    criteria.max_touchdown_speed,
+                    criteria.max_tilt_degrees,
+                    if criteria.require_pad { ", on a pad" } else { "" },
+                ),
+                TextStyle {
+                    font_size: 24.,
+                    ..default()
+                },
+            ),
+        ])
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(25.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+        StateScoped(GameState::Briefing),
+    ));
+}
+
+fn briefing_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    catalog: Res<MissionCatalog>,
+    selected: Res<SelectedMission>,
+    settings: Res<Settings>,
+    mut mission: ResMut<ActiveMission>,
+    mut difficulty: ResMut<ActiveDifficulty>,
+    mut mission_started: EventWriter<MissionStarted>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.set(GameState::MainMenu);
+    } else if keyboard_input.just_pressed(KeyCode::Enter) {
+        difficulty.0 = settings.difficulty.preset();
+        mission.0 = difficulty.0.apply(&catalog.missions[selected.0]);
+        log::info!(
+            "Starting mission {:?} on {:?}",
+            mission.0.name,
+            settings.difficulty
+        );
+        mission_started.send(MissionStarted);
+        state.set(GameState::Playing);
+    }
 }
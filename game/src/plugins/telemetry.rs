@@ -1,113 +1,462 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use bevy::{log, prelude::*};
-use serde::Serialize;
-use tokio::io::AsyncWriteExt;
+use bevy_rapier3d::dynamics::Velocity as BodyVelocity;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpListener;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 
 use super::{
+    altimeter::RadarAltimeter,
+    ground_control::GroundCommand,
+    mission::{ActiveMission, TargetPad},
     rocket::*,
-    weather::{WindDirection, WindSpeed},
+    splash::GameState,
+    weather::{CurrentWeather, Dust, WindDirection, WindSpeed},
 };
-#[derive(Debug, Clone, Serialize)]
+
+pub const TELEMETRY_ADDRESS: &str = "127.0.0.1:8088";
+const SOURCE_QUEUE_CAPACITY: usize = 32;
+const CLIENT_QUEUE_CAPACITY: usize = 64;
+// largest sensor errors in a full dust storm
+const ALTITUDE_DUST_NOISE: f32 = 1.0;
+const VELOCITY_DUST_NOISE: f32 = 0.3;
+
+/// Bumped whenever fields are added, removed or reordered. It is always the
+/// first field of a sample so clients can check it before decoding the rest.
+pub const TELEMETRY_SCHEMA_VERSION: u16 = 5;
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct TelemetryData {
+    pub schema_version: u16,
+    pub sequence: u64,
+    pub dropped_samples: u64,
+    pub mission_time: f32,
+    pub game_state: GameState,
     pub fuel: f32,
     pub altitude: f32,
+    /// Height above the ground below, if any is in radar range.
+    pub radar_altitude: Option<f32>,
+    /// Slope of the ground below in degrees.
+    pub terrain_slope: f32,
+    /// Seconds to touchdown at the current sink rate.
+    pub time_to_impact: Option<f32>,
+    /// Rocket position relative to the target pad.
+    pub position: Vec3,
     pub velocity: Vec3,
+    pub attitude: Quat,
+    /// Pitch, yaw and roll in degrees.
+    pub attitude_euler: Vec3,
+    pub angular_velocity: Vec3,
     pub thrust: f32,
     pub left_ecs: f32,
     pub right_ecs: f32,
     pub wind_speed: f32,
     pub wind_direction: Vec3,
+    pub temp_c: f32,
+    /// Airborne dust, 0 for clear air to 1 for a full storm.
+    pub dust_opacity: f32,
+    /// Events that happened since the previous sample.
+    pub events: Vec<FlightEvent>,
 }
 
-#[derive(Resource)]
-pub struct TelemetryChannel {
-    pub tx: mpsc::Sender<TelemetryData>,
+/// What to do with new samples when a client can't keep up.
+///
+/// A client picks its own strategy by sending a line such as
+/// `backpressure latest_only` at any time after connecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Keep a bounded queue and discard the oldest samples first.
+    #[default]
+    DropOldest,
+    /// Only ever keep the most recent sample.
+    LatestOnly,
+    /// Wait for the client, pacing every other client with it. Samples are
+    /// then dropped at the source once the whole pipeline is full.
+    Block,
 }
 
-impl TelemetryChannel {
-    pub fn new() -> Self {
-        let (tx, mut rx) = mpsc::channel::<TelemetryData>(32);
-        tokio::spawn(async move {
-            let listener = TcpListener::bind("127.0.0.1:8088").await.unwrap();
-            let mut last_recieved_data = TelemetryData {
-                fuel: 0.0,
-                altitude: 0.0,
-                velocity: Vec3::ZERO,
-                thrust: 0.0,
-                left_ecs: 0.0,
-                right_ecs: 0.0,
-                wind_speed: 0.0,
-                wind_direction: Vec3::ZERO,
-            };
-
-            match listener.accept().await {
-                Ok((mut _socket, _addr)) => {
-                    while let Some(telemetry) = rx.recv().await {
-                        last_recieved_data = telemetry;
-
-                        if _socket
-                            .write_all(&bincode::serialize(&last_recieved_data).unwrap_or_default())
-                            .await
-                            .is_err()
-                        {
+impl FromStr for Backpressure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop_oldest" => Ok(Backpressure::DropOldest),
+            "latest_only" => Ok(Backpressure::LatestOnly),
+            "block" => Ok(Backpressure::Block),
+            _ => Err(format!("unknown backpressure strategy: {}", s)),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TelemetrySettings {
+    pub rate_hz: f32,
+    pub backpressure: Backpressure,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            rate_hz: 20.0,
+            backpressure: Backpressure::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ClientQueue {
+    samples: VecDeque<TelemetryData>,
+    backpressure: Backpressure,
+    dropped: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct TelemetryClient {
+    queue: Mutex<ClientQueue>,
+    sample_ready: Notify,
+    space_ready: Notify,
+}
+
+impl TelemetryClient {
+    fn new(backpressure: Backpressure) -> Self {
+        Self {
+            queue: Mutex::new(ClientQueue {
+                backpressure,
+                ..default()
+            }),
+            ..default()
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.queue.lock().map(|queue| queue.closed).unwrap_or(true)
+    }
+
+    fn close(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.closed = true;
+        }
+        self.space_ready.notify_one();
+    }
+
+    fn set_backpressure(&self, backpressure: Backpressure) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.backpressure = backpressure;
+        }
+        self.space_ready.notify_one();
+    }
+
+    async fn push(&self, sample: TelemetryData) {
+        loop {
+            {
+                let Ok(mut queue) = self.queue.lock() else {
+                    return;
+                };
+                if queue.closed {
+                    return;
+                }
+
+                match queue.backpressure {
+                    Backpressure::DropOldest => {
+                        while queue.samples.len() >= CLIENT_QUEUE_CAPACITY {
+                            queue.samples.pop_front();
+                            queue.dropped += 1;
+                        }
+                        queue.samples.push_back(sample);
+                        break;
+                    }
+                    Backpressure::LatestOnly => {
+                        queue.dropped += queue.samples.len() as u64;
+                        queue.samples.clear();
+                        queue.samples.push_back(sample);
+                        break;
+                    }
+                    Backpressure::Block => {
+                        if queue.samples.len() < CLIENT_QUEUE_CAPACITY {
+                            queue.samples.push_back(sample);
                             break;
                         }
                     }
                 }
-                Err(e) => {
-                    log::error!("Failed to accept connection: {:?}", e);
-                }
             }
-        });
-        Self { tx }
+            self.space_ready.notified().await;
+        }
+        self.sample_ready.notify_one();
+    }
+
+    fn pop(&self) -> Option<(TelemetryData, u64)> {
+        let mut queue = self.queue.lock().ok()?;
+        let sample = queue.samples.pop_front()?;
+        Some((sample, queue.dropped))
+    }
+}
+
+#[derive(Resource)]
+pub struct TelemetryChannel {
+    pub tx: mpsc::Sender<TelemetryData>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl TelemetryChannel {
+    pub fn new(address: &str, backpressure: Backpressure) -> Self {
+        let (tx, rx) = mpsc::channel::<TelemetryData>(SOURCE_QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let clients = Arc::new(Mutex::new(Vec::<Arc<TelemetryClient>>::new()));
+
+        tokio::spawn(accept_clients(
+            address.to_string(),
+            clients.clone(),
+            dropped.clone(),
+            backpressure,
+        ));
+        tokio::spawn(fan_out(rx, clients));
+
+        Self { tx, dropped }
     }
 
     pub fn send_telemetry_data(&self, data: TelemetryData) {
-        let tx = self.tx.clone();
-        match tx.try_send(data) {
-            Ok(_) => {
-                // log::info!("Telemetry data sent");
+        if self.tx.try_send(data).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn accept_clients(
+    address: String,
+    clients: Arc<Mutex<Vec<Arc<TelemetryClient>>>>,
+    dropped: Arc<AtomicU64>,
+    backpressure: Backpressure,
+) {
+    let listener = match TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to bind telemetry on {}: {:?}", address, e);
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                log::info!("Telemetry client connected from {}", addr);
+                let client = Arc::new(TelemetryClient::new(backpressure));
+                let (reader, writer) = socket.into_split();
+                tokio::spawn(read_client_requests(client.clone(), reader));
+                tokio::spawn(write_samples(client.clone(), writer, dropped.clone()));
+                if let Ok(mut clients) = clients.lock() {
+                    clients.push(client);
+                }
             }
             Err(e) => {
-                // log::error!("Failed to send telemetry data: {:?}", e);
+                log::error!("Failed to accept connection: {:?}", e);
+            }
+        }
+    }
+}
+
+async fn fan_out(
+    mut rx: mpsc::Receiver<TelemetryData>,
+    clients: Arc<Mutex<Vec<Arc<TelemetryClient>>>>,
+) {
+    while let Some(sample) = rx.recv().await {
+        let connected = match clients.lock() {
+            Ok(mut clients) => {
+                clients.retain(|client| !client.is_closed());
+                clients.clone()
+            }
+            Err(_) => break,
+        };
+
+        for client in connected {
+            client.push(sample.clone()).await;
+        }
+    }
+}
+
+async fn read_client_requests(client: Arc<TelemetryClient>, reader: OwnedReadHalf) {
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        match line.trim().strip_prefix("backpressure ") {
+            Some(strategy) => match strategy.trim().parse::<Backpressure>() {
+                Ok(backpressure) => client.set_backpressure(backpressure),
+                Err(e) => log::warn!("Telemetry client request rejected: {}", e),
+            },
+            None => log::warn!("Unknown telemetry client request: {}", line),
+        }
+    }
+}
+
+async fn write_samples(
+    client: Arc<TelemetryClient>,
+    mut writer: OwnedWriteHalf,
+    source_dropped: Arc<AtomicU64>,
+) {
+    loop {
+        let Some((mut sample, dropped)) = client.pop() else {
+            if client.is_closed() {
+                break;
             }
+            client.sample_ready.notified().await;
+            continue;
+        };
+        client.space_ready.notify_one();
+
+        sample.dropped_samples = dropped + source_dropped.load(Ordering::Relaxed);
+        let payload = bincode::serialize(&sample).unwrap_or_default();
+
+        // each sample is framed with its length so clients don't need to
+        // know the schema to split the stream
+        if writer.write_u32_le(payload.len() as u32).await.is_err()
+            || writer.write_all(&payload).await.is_err()
+        {
+            break;
         }
     }
+
+    client.close();
 }
 
-pub struct TelemetryPlugin;
+#[derive(Resource)]
+struct TelemetryTimer(Timer);
+
+pub struct TelemetryPlugin {
+    pub address: String,
+    pub settings: TelemetrySettings,
+}
+
+impl Default for TelemetryPlugin {
+    fn default() -> Self {
+        Self {
+            address: TELEMETRY_ADDRESS.to_string(),
+            settings: TelemetrySettings::default(),
+        }
+    }
+}
 
 impl Plugin for TelemetryPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(TelemetryChannel::new())
-            .add_systems(Update, broadcast_telemetry_system);
+        app.insert_resource(TelemetryChannel::new(
+            &self.address,
+            self.settings.backpressure,
+        ))
+        .insert_resource(self.settings)
+        .insert_resource(TelemetryTimer(Timer::from_seconds(
+            1.0 / self.settings.rate_hz,
+            TimerMode::Repeating,
+        )))
+        .add_systems(
+            Update,
+            (
+                ground_telemetry_rate_system,
+                broadcast_telemetry_system.after(ground_telemetry_rate_system),
+            ),
+        );
+    }
+}
+
+fn ground_telemetry_rate_system(
+    mut ground_commands: EventReader<GroundCommand>,
+    mut settings: ResMut<TelemetrySettings>,
+    mut timer: ResMut<TelemetryTimer>,
+) {
+    for command in ground_commands.read() {
+        if let GroundCommand::SetTelemetryRate { hz } = command {
+            settings.rate_hz = *hz;
+            timer.0.set_duration(Duration::from_secs_f32(1.0 / hz));
+        }
     }
 }
 
 fn broadcast_telemetry_system(
-    rocket_telemetry_query: Query<(&Fuel, &Thrust, &LeftEcs, &RightEcs, &Velocity, &Altitute)>,
+    time: Res<Time>,
+    mut timer: ResMut<TelemetryTimer>,
+    mut sequence: Local<u64>,
+    mut pending_events: Local<Vec<FlightEvent>>,
+    mut flight_events: EventReader<FlightEvent>,
+    mission_time: Res<MissionTime>,
+    game_state: Res<State<GameState>>,
+    current_weather: Res<CurrentWeather>,
+    dust: Res<Dust>,
+    mission: Res<ActiveMission>,
+    target: Res<TargetPad>,
+    rocket_telemetry_query: Query<
+        (
+            &Transform,
+            &Fuel,
+            &Thrust,
+            &LeftEcs,
+            &RightEcs,
+            &Velocity,
+            &Altitute,
+            &RadarAltimeter,
+        ),
+        With<Rocket>,
+    >,
+    body_query: Query<&BodyVelocity, With<RocketCollider>>,
     wind_query: Query<(&WindSpeed, &WindDirection)>,
     telemetry_channel: Res<TelemetryChannel>,
 ) {
+    pending_events.extend(flight_events.read().copied());
+
+    // above the frame rate several samples fall due in one frame; they all
+    // carry that frame's state so the stream keeps the requested rate
+    let samples = timer.0.tick(time.delta()).times_finished_this_tick();
+    if samples == 0 {
+        return;
+    }
+
     let mut telemetry_data = TelemetryData {
-        fuel: 0.0,
-        altitude: 0.0,
-        velocity: Vec3::ZERO,
-        thrust: 0.0,
-        left_ecs: 0.0,
-        right_ecs: 0.0,
-        wind_speed: 0.0,
-        wind_direction: Vec3::ZERO,
+        schema_version: TELEMETRY_SCHEMA_VERSION,
+        mission_time: mission_time.seconds,
+        game_state: *game_state.get(),
+        temp_c: current_weather.temp_c,
+        dust_opacity: dust.opacity,
+        events: std::mem::take(&mut *pending_events),
+        ..default()
     };
 
-    for (fuel, thrust, left_ecs, right_ecs, velocity, altitude) in rocket_telemetry_query.iter() {
+    let pad_position = target
+        .spec(&mission.0)
+        .map(|pad| pad.position)
+        .unwrap_or_default();
+
+    for (transform, fuel, thrust, left_ecs, right_ecs, velocity, altitude, radar) in
+        rocket_telemetry_query.iter()
+    {
+        let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::YXZ);
+        telemetry_data.position = transform.translation - pad_position;
+        telemetry_data.attitude = transform.rotation;
+        telemetry_data.attitude_euler =
+            Vec3::new(pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees());
         telemetry_data.fuel = fuel.value;
         telemetry_data.thrust = thrust.value;
         telemetry_data.left_ecs = left_ecs.value;
         telemetry_data.right_ecs = right_ecs.value;
-        telemetry_data.velocity = velocity.value;
-        telemetry_data.altitude = altitude.value;
+        // dust blinds the sensors as well as the pilot
+        telemetry_data.velocity = velocity.value
+            + Vec3::new(
+                dust.sensor_noise(VELOCITY_DUST_NOISE),
+                dust.sensor_noise(VELOCITY_DUST_NOISE),
+                dust.sensor_noise(VELOCITY_DUST_NOISE),
+            );
+        telemetry_data.altitude = altitude.value + dust.sensor_noise(ALTITUDE_DUST_NOISE);
+        telemetry_data.radar_altitude = radar.height;
+        telemetry_data.terrain_slope = radar.slope_degrees;
+        telemetry_data.time_to_impact = radar.time_to_impact;
+    }
+
+    for body_velocity in body_query.iter() {
+        telemetry_data.angular_velocity = body_velocity.angvel;
     }
 
     for (wind_speed, wind_direction) in wind_query.iter() {
@@ -115,5 +464,13 @@ fn broadcast_telemetry_system(
         telemetry_data.wind_direction = wind_direction.value;
     }
 
-    telemetry_channel.send_telemetry_data(telemetry_data);
+    for _ in 0..samples {
+        *sequence += 1;
+        telemetry_channel.send_telemetry_data(TelemetryData {
+            sequence: *sequence,
+            ..telemetry_data.clone()
+        });
+        // events are only reported once
+        telemetry_data.events.clear();
+    }
 }
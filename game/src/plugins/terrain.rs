@@ -1,47 +1,288 @@
 use bevy::{log, prelude::*};
 
 use bevy_rapier3d::prelude::*;
+use serde_derive::Deserialize;
+
+use super::{
+    difficulty::ActiveDifficulty,
+    gravity::PlanetGravity,
+    mission::{ActiveMission, MissionStarted, PadSpec},
+    terrain_generator::{GeneratedTerrain, TerrainSettings},
+};
 
 pub struct TerrainPlugin;
 
+/// Where the landing site comes from.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainSource {
+    /// A hand-made scene, with an optional invisible collision mesh.
+    Asset {
+        scene: String,
+        collider: Option<String>,
+    },
+    Procedural {
+        seed: u64,
+    },
+}
+
+impl Default for TerrainSource {
+    fn default() -> Self {
+        TerrainSource::Asset {
+            scene: "Planet3.glb".to_string(),
+            collider: Some("PlanetCollider.glb".to_string()),
+        }
+    }
+}
+
+/// Everything that makes up the ground, visible or not.
+#[derive(Component)]
+pub struct Terrain;
+
+#[derive(Component)]
+pub struct LandingPad {
+    pub radius: f32,
+    pub score_multiplier: f32,
+}
+
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (setup_assets, setup_colliders));
-        app.add_systems(Update, display_events);
+        app.add_systems(
+            Update,
+            (
+                apply_mission_terrain_system,
+                display_events,
+                terrain_colliders_ready_system,
+            ),
+        );
     }
 }
 
 #[derive(Bundle)]
 struct TerrainBundle {
     scene: SceneBundle,
+    terrain: Terrain,
 }
 
-fn setup_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
-    log::info!("Loading assets...");
+/// Invisible scene whose meshes are turned into trimesh colliders once loaded.
+#[derive(Component)]
+struct TerrainColliderScene;
+
+/// Idealised planet used until the mesh colliders are ready.
+#[derive(Component)]
+struct FallbackPlanetCollider;
+
+fn apply_mission_terrain_system(
+    mut commands: Commands,
+    mut mission_started: EventReader<MissionStarted>,
+    mut current: Local<Option<(TerrainSource, Vec<PadSpec>)>>,
+    mission: Res<ActiveMission>,
+    difficulty: Res<ActiveDifficulty>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut gravity: ResMut<PlanetGravity>,
+    terrain: Query<Entity, With<Terrain>>,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+    gravity.surface_gravity = difficulty.0.gravity;
+
+    // restarting the same site keeps what is already loaded
+    let site = (mission.0.terrain.clone(), mission.0.pads.clone());
+    if current.as_ref() == Some(&site) {
+        return;
+    }
+
+    for entity in terrain.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    match &site.0 {
+        TerrainSource::Asset { scene, collider } => {
+            *gravity = PlanetGravity {
+                surface_gravity: gravity.surface_gravity,
+                ..default()
+            };
+            spawn_asset_terrain(&mut commands, &asset_server, scene, collider.as_deref());
+        }
+        TerrainSource::Procedural { seed } => {
+            // the generated site is flat, so pull straight down from far below
+            *gravity = PlanetGravity {
+                center: Vec3::new(0.0, -5000.0, 0.0),
+                radius: 5000.0,
+                ..*gravity
+            };
+            spawn_procedural_terrain(&mut commands, &mut meshes, &mut materials, *seed, &site.1);
+        }
+    }
+
+    spawn_pads(&mut commands, &mut meshes, &mut materials, &site.1);
+    *current = Some(site);
+}
+
+fn spawn_asset_terrain(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    scene: &str,
+    collider: Option<&str>,
+) {
+    log::info!("Loading terrain {}", scene);
 
     commands.spawn(TerrainBundle {
         scene: SceneBundle {
-            scene: asset_server.load("Planet3.glb#Scene0"),
+            scene: asset_server.load(format!("{}#Scene0", scene)),
             transform: Transform::from_xyz(0.0, 0.0, 0.0),
             ..default()
         },
+        terrain: Terrain,
     });
-}
 
-fn setup_colliders(mut commands: Commands) {
+    // Collision mesh matching the visible terrain
+    if let Some(collider) = collider {
+        commands.spawn((
+            SceneBundle {
+                scene: asset_server.load(format!("{}#Scene0", collider)),
+                transform: Transform::from_xyz(0.0, 0.0, 0.0),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            AsyncSceneCollider::default(),
+            TerrainColliderScene,
+            Terrain,
+        ));
+    }
+
     // Sphere planet
     commands
         .spawn(Collider::ball(25.0))
         .insert(TransformBundle::from(Transform::from_xyz(0.0, -25.0, 0.0)))
         .insert(ActiveEvents::COLLISION_EVENTS)
-        .insert(Name::new("Planet"));
+        .insert(Name::new("Planet"))
+        .insert(FallbackPlanetCollider)
+        .insert(Terrain);
+}
 
-    // Launch pad
-    commands
-        .spawn(Collider::cylinder(0.2, 1.2))
-        .insert(TransformBundle::from(Transform::from_xyz(0.0, 0.0, 0.0)))
-        .insert(ActiveEvents::COLLISION_EVENTS)
-        .insert(Name::new("Launch Pad"));
+fn spawn_procedural_terrain(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    seed: u64,
+    pads: &[PadSpec],
+) {
+    log::info!("Generating terrain with seed {}", seed);
+    let pad_sites: Vec<(Vec2, f32)> = pads
+        .iter()
+        .map(|pad| (Vec2::new(pad.position.x, pad.position.z), pad.radius))
+        .collect();
+    let terrain = GeneratedTerrain::generate(seed, &TerrainSettings::default(), &pad_sites);
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(terrain.heightfield.to_mesh()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::srgb(0.58, 0.3, 0.18),
+                perceptual_roughness: 0.95,
+                ..default()
+            }),
+            ..default()
+        },
+        terrain.heightfield.to_collider(),
+        ActiveEvents::COLLISION_EVENTS,
+        Name::new("Planet"),
+        Terrain,
+    ));
+
+    let boulder_mesh = meshes.add(Sphere::new(1.0).mesh().ico(2).unwrap());
+    let boulder_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.42, 0.24, 0.16),
+        perceptual_roughness: 1.0,
+        ..default()
+    });
+
+    for boulder in terrain.boulders.iter() {
+        commands.spawn((
+            PbrBundle {
+                mesh: boulder_mesh.clone(),
+                material: boulder_material.clone(),
+                transform: Transform::from_translation(boulder.position)
+                    .with_scale(Vec3::splat(boulder.radius)),
+                ..default()
+            },
+            Collider::ball(1.0),
+            ActiveEvents::COLLISION_EVENTS,
+            Name::new("Boulder"),
+            Terrain,
+        ));
+    }
+}
+
+fn spawn_pads(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    pads: &[PadSpec],
+) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.55, 0.55, 0.58),
+        perceptual_roughness: 0.8,
+        ..default()
+    });
+
+    for pad in pads.iter() {
+        let mut entity = commands.spawn((
+            Collider::cylinder(0.2, pad.radius),
+            TransformBundle::from(Transform::from_translation(pad.position)),
+            ActiveEvents::COLLISION_EVENTS,
+            Name::new(pad.name.clone()),
+            LandingPad {
+                radius: pad.radius,
+                score_multiplier: pad.score_multiplier,
+            },
+            Terrain,
+        ));
+
+        if pad.marker {
+            entity.insert((
+                meshes.add(Cylinder::new(pad.radius, 0.4)),
+                material.clone(),
+                VisibilityBundle::default(),
+            ));
+        }
+    }
+}
+
+fn terrain_colliders_ready_system(
+    mut commands: Commands,
+    added_colliders: Query<Entity, Added<Collider>>,
+    parents: Query<&Parent>,
+    collider_scenes: Query<(), With<TerrainColliderScene>>,
+    fallback: Query<Entity, With<FallbackPlanetCollider>>,
+) {
+    let mut ready = false;
+
+    for entity in added_colliders.iter() {
+        if !parents
+            .iter_ancestors(entity)
+            .any(|ancestor| collider_scenes.contains(ancestor))
+        {
+            continue;
+        }
+
+        commands
+            .entity(entity)
+            .insert(ActiveEvents::COLLISION_EVENTS)
+            .insert(Name::new("Planet"));
+        ready = true;
+    }
+
+    if ready {
+        for entity in fallback.iter() {
+            log::info!("Terrain mesh colliders ready, removing fallback planet");
+            commands.entity(entity).despawn();
+        }
+    }
 }
 
 fn display_events(mut collision_events: EventReader<CollisionEvent>, _query: Query<&Name>) {
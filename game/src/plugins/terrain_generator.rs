@@ -0,0 +1,382 @@
+use bevy::{
+    log,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use bevy_rapier3d::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// boulder positions tried per boulder before giving up on the rest, for
+// sites mostly covered by pads
+const BOULDER_ATTEMPTS: usize = 20;
+
+/// Shape of a generated landing site.
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainSettings {
+    /// Width and depth of the square site.
+    pub size: f32,
+    /// Height samples along each side.
+    pub resolution: usize,
+    /// Height of the rolling hills.
+    pub amplitude: f32,
+    pub craters: usize,
+    pub boulders: usize,
+    /// Smallest area kept flat around each pad.
+    pub pad_radius: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            size: 48.0,
+            resolution: 129,
+            amplitude: 1.6,
+            craters: 9,
+            boulders: 40,
+            pad_radius: 2.5,
+        }
+    }
+}
+
+/// Square grid of heights, stored row by row along +Z.
+pub struct Heightfield {
+    pub resolution: usize,
+    pub size: f32,
+    pub heights: Vec<f32>,
+}
+
+pub struct Boulder {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+pub struct GeneratedTerrain {
+    pub heightfield: Heightfield,
+    pub boulders: Vec<Boulder>,
+}
+
+impl GeneratedTerrain {
+    /// `pads` are the centres and radii of areas to keep flat.
+    pub fn generate(seed: u64, settings: &TerrainSettings, pads: &[(Vec2, f32)]) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let size = if settings.size > 0.0 {
+            settings.size
+        } else {
+            log::warn!(
+                "Terrain size {} is not positive, using the default",
+                settings.size
+            );
+            TerrainSettings::default().size
+        };
+        let resolution = settings.resolution.max(2);
+        let step = size / (resolution - 1) as f32;
+        let half = size / 2.0;
+        let pads: Vec<(Vec2, f32)> = pads
+            .iter()
+            .map(|(center, radius)| (*center, radius.max(settings.pad_radius)))
+            .collect();
+        let near_pad = |point: Vec2| {
+            pads.iter()
+                .any(|(center, radius)| point.distance(*center) < radius * 2.0)
+        };
+
+        let craters: Vec<(Vec2, f32, f32)> = (0..settings.craters)
+            .map(|_| {
+                let center = Vec2::new(rng.gen_range(-half..half), rng.gen_range(-half..half));
+                let radius = rng.gen_range(1.5..5.5);
+                let depth = radius * rng.gen_range(0.12..0.25);
+                (center, radius, depth)
+            })
+            .collect();
+
+        let mut heights = Vec::with_capacity(resolution * resolution);
+        for z in 0..resolution {
+            for x in 0..resolution {
+                let point = Vec2::new(-half + x as f32 * step, -half + z as f32 * step);
+                let mut height = fbm(seed, point * 0.08, 5) * settings.amplitude;
+
+                for (center, radius, depth) in craters.iter() {
+                    height += crater_profile(point.distance(*center) / radius) * depth;
+                }
+
+                // blend into a flat area around each pad
+                let pad_blend = pads
+                    .iter()
+                    .map(|(center, radius)| {
+                        smoothstep(*radius, radius * 2.0, point.distance(*center))
+                    })
+                    .product::<f32>();
+                heights.push(height * pad_blend);
+            }
+        }
+
+        let heightfield = Heightfield {
+            resolution,
+            size,
+            heights,
+        };
+
+        let mut boulders = Vec::with_capacity(settings.boulders);
+        let mut attempts = settings.boulders * BOULDER_ATTEMPTS;
+        while boulders.len() < settings.boulders {
+            if attempts == 0 {
+                log::warn!(
+                    "Only found room for {} of {} boulders around the pads",
+                    boulders.len(),
+                    settings.boulders
+                );
+                break;
+            }
+            attempts -= 1;
+
+            let point = Vec2::new(rng.gen_range(-half..half), rng.gen_range(-half..half));
+            if near_pad(point) {
+                continue;
+            }
+
+            let radius = rng.gen_range(0.08..0.45);
+            // sink them a little so they sit in the ground
+            let height = heightfield.height_at(point) + radius * 0.4;
+            boulders.push(Boulder {
+                position: Vec3::new(point.x, height, point.y),
+                radius,
+            });
+        }
+
+        Self {
+            heightfield,
+            boulders,
+        }
+    }
+}
+
+impl Heightfield {
+    fn sample(&self, x: usize, z: usize) -> f32 {
+        let x = x.min(self.resolution - 1);
+        let z = z.min(self.resolution - 1);
+        self.heights[z * self.resolution + x]
+    }
+
+    /// Bilinearly interpolated height at a world position.
+    pub fn height_at(&self, point: Vec2) -> f32 {
+        let step = self.size / (self.resolution - 1) as f32;
+        let grid = ((point + self.size / 2.0) / step)
+            .clamp(Vec2::ZERO, Vec2::splat((self.resolution - 1) as f32));
+        let (x, z) = (grid.x.floor() as usize, grid.y.floor() as usize);
+        let (fx, fz) = (grid.x.fract(), grid.y.fract());
+
+        let top = self.sample(x, z).lerp(self.sample(x + 1, z), fx);
+        let bottom = self.sample(x, z + 1).lerp(self.sample(x + 1, z + 1), fx);
+        top.lerp(bottom, fz)
+    }
+
+    pub fn to_mesh(&self) -> Mesh {
+        let resolution = self.resolution;
+        let step = self.size / (resolution - 1) as f32;
+        let half = self.size / 2.0;
+
+        let mut positions = Vec::with_capacity(resolution * resolution);
+        let mut normals = Vec::with_capacity(resolution * resolution);
+        let mut uvs = Vec::with_capacity(resolution * resolution);
+
+        for z in 0..resolution {
+            for x in 0..resolution {
+                positions.push([
+                    -half + x as f32 * step,
+                    self.sample(x, z),
+                    -half + z as f32 * step,
+                ]);
+
+                let left = self.sample(x.saturating_sub(1), z);
+                let right = self.sample(x + 1, z);
+                let back = self.sample(x, z.saturating_sub(1));
+                let front = self.sample(x, z + 1);
+                normals.push(
+                    Vec3::new(left - right, 2.0 * step, back - front)
+                        .normalize()
+                        .to_array(),
+                );
+
+                uvs.push([
+                    x as f32 / (resolution - 1) as f32,
+                    z as f32 / (resolution - 1) as f32,
+                ]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity((resolution - 1) * (resolution - 1) * 6);
+        for z in 0..resolution - 1 {
+            for x in 0..resolution - 1 {
+                let i0 = (z * resolution + x) as u32;
+                let i1 = i0 + 1;
+                let i2 = i0 + resolution as u32;
+                let i3 = i2 + 1;
+                indices.extend_from_slice(&[i0, i2, i1, i1, i2, i3]);
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    pub fn to_collider(&self) -> Collider {
+        // Rapier wants rows along Z and columns along X, in column-major order
+        let mut heights = Vec::with_capacity(self.heights.len());
+        for x in 0..self.resolution {
+            for z in 0..self.resolution {
+                heights.push(self.sample(x, z));
+            }
+        }
+
+        Collider::heightfield(
+            heights,
+            self.resolution,
+            self.resolution,
+            Vec3::new(self.size, 1.0, self.size),
+        )
+    }
+}
+
+/// Bowl with a raised rim, `distance` is relative to the crater radius.
+fn crater_profile(distance: f32) -> f32 {
+    if distance < 1.0 {
+        distance * distance - 1.0
+    } else if distance < 1.6 {
+        let rim = 1.0 - (distance - 1.0) / 0.6;
+        0.35 * rim * rim
+    } else {
+        0.0
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Pseudo-random value in -1..1 for a lattice point.
+fn lattice(seed: u64, x: i32, z: i32) -> f32 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+    (hash >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+fn value_noise(seed: u64, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let (x, z) = (cell.x as i32, cell.y as i32);
+    let t = point - cell;
+    let t = t * t * (Vec2::splat(3.0) - 2.0 * t);
+
+    let top = lattice(seed, x, z).lerp(lattice(seed, x + 1, z), t.x);
+    let bottom = lattice(seed, x, z + 1).lerp(lattice(seed, x + 1, z + 1), t.x);
+    top.lerp(bottom, t.y)
+}
+
+fn fbm(seed: u64, point: Vec2, octaves: u32) -> f32 {
+    let mut value = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for octave in 0..octaves {
+        value += value_noise(seed.wrapping_add(octave as u64), point * frequency) * amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_settings() -> TerrainSettings {
+        TerrainSettings {
+            resolution: 33,
+            boulders: 10,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_terrain() {
+        let pads = [(Vec2::ZERO, 1.5)];
+        let first = GeneratedTerrain::generate(1977, &small_settings(), &pads);
+        let second = GeneratedTerrain::generate(1977, &small_settings(), &pads);
+
+        assert_eq!(first.heightfield.heights, second.heightfield.heights);
+        let boulders = |terrain: &GeneratedTerrain| {
+            terrain
+                .boulders
+                .iter()
+                .map(|boulder| (boulder.position, boulder.radius))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(boulders(&first), boulders(&second));
+    }
+
+    #[test]
+    fn different_seeds_give_different_terrain() {
+        let first = GeneratedTerrain::generate(1, &small_settings(), &[]);
+        let second = GeneratedTerrain::generate(2, &small_settings(), &[]);
+
+        assert_ne!(first.heightfield.heights, second.heightfield.heights);
+    }
+
+    #[test]
+    fn pad_sites_are_flat() {
+        let settings = small_settings();
+        let pads = [(Vec2::ZERO, 1.5), (Vec2::new(12.0, -8.0), 1.0)];
+        let terrain = GeneratedTerrain::generate(1977, &settings, &pads);
+        let heightfield = &terrain.heightfield;
+        let step = settings.size / (heightfield.resolution - 1) as f32;
+        let half = settings.size / 2.0;
+
+        let mut checked = 0;
+        for z in 0..heightfield.resolution {
+            for x in 0..heightfield.resolution {
+                let point = Vec2::new(-half + x as f32 * step, -half + z as f32 * step);
+                // pads are kept flat out to at least `pad_radius`
+                if pads.iter().any(|(center, radius)| {
+                    point.distance(*center) <= radius.max(settings.pad_radius)
+                }) {
+                    assert_eq!(heightfield.sample(x, z), 0.0, "cell {}, {}", x, z);
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn site_covered_by_pads_has_no_boulders() {
+        let terrain = GeneratedTerrain::generate(1977, &small_settings(), &[(Vec2::ZERO, 40.0)]);
+
+        assert!(terrain.boulders.is_empty());
+    }
+
+    #[test]
+    fn non_positive_size_falls_back_to_default() {
+        let settings = TerrainSettings {
+            size: 0.0,
+            ..small_settings()
+        };
+        let terrain = GeneratedTerrain::generate(1977, &settings, &[]);
+
+        assert_eq!(terrain.heightfield.size, TerrainSettings::default().size);
+        assert_eq!(terrain.boulders.len(), settings.boulders);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy_rapier3d::{dynamics::Velocity as BodyVelocity, prelude::*};
+
+use super::{
+    gravity::PlanetGravity,
+    landing_compass::ApproachSafety,
+    mission::ActiveMission,
+    rocket::{Fuel, Rocket, RocketCollider, Thrust, LINEAR_DAMPING, ROCKET_MASS},
+    settings::Settings,
+    splash::GameState,
+    weather::{WindDirection, WindSpeed},
+};
+
+// how far ahead the flight path is followed
+const PREDICTION_STEP: f32 = 0.05;
+const PREDICTION_HORIZON: f32 = 8.0;
+const PATH_COLOR_MISS: Color = Color::srgba(0.6, 0.8, 1.0, 0.6);
+
+/// Where the predicted path meets the ground.
+#[derive(Debug, Clone, Copy)]
+pub struct PredictedImpact {
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Speed at touchdown in m/s.
+    pub speed: f32,
+    /// Seconds from now.
+    pub time: f32,
+}
+
+/// Flight path the rocket follows if thrust, attitude and wind stay as they
+/// are. Empty while the prediction assist is off.
+#[derive(Resource, Default)]
+pub struct PredictedTrajectory {
+    pub points: Vec<Vec3>,
+    pub impact: Option<PredictedImpact>,
+}
+
+pub struct TrajectoryPlugin;
+
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PredictedTrajectory>()
+            .add_systems(
+                Update,
+                (predict_trajectory_system, draw_trajectory_system)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_trajectory);
+    }
+}
+
+/// Steps the same forces as `applied_physics_forces_system` forward, with
+/// `acceleration` standing for everything but gravity.
+pub fn predict_trajectory(
+    rapier_context: &RapierContext,
+    gravity: &PlanetGravity,
+    mut position: Vec3,
+    mut velocity: Vec3,
+    acceleration: Vec3,
+) -> PredictedTrajectory {
+    let steps = (PREDICTION_HORIZON / PREDICTION_STEP) as usize;
+    let mut trajectory = PredictedTrajectory {
+        points: vec![position],
+        impact: None,
+    };
+
+    for step in 1..=steps {
+        velocity += (gravity.acceleration_at(position) + acceleration) * PREDICTION_STEP;
+        velocity /= 1.0 + PREDICTION_STEP * LINEAR_DAMPING;
+        let displacement = velocity * PREDICTION_STEP;
+
+        if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+            position,
+            displacement,
+            1.0,
+            true,
+            QueryFilter::only_fixed(),
+        ) {
+            trajectory.points.push(hit.point);
+            trajectory.impact = Some(PredictedImpact {
+                point: hit.point,
+                normal: hit.normal,
+                speed: velocity.length(),
+                time: step as f32 * PREDICTION_STEP,
+            });
+            return trajectory;
+        }
+        position += displacement;
+        trajectory.points.push(position);
+    }
+    trajectory
+}
+
+pub fn predict_trajectory_system(
+    settings: Res<Settings>,
+    rapier_context: Res<RapierContext>,
+    gravity: Res<PlanetGravity>,
+    mut trajectory: ResMut<PredictedTrajectory>,
+    body: Query<(&Transform, &BodyVelocity), With<RocketCollider>>,
+    rocket_query: Query<(&Thrust, &Fuel), With<Rocket>>,
+    wind_query: Query<(&WindDirection, &WindSpeed)>,
+) {
+    if !settings.assists.trajectory_prediction {
+        if !trajectory.points.is_empty() {
+            *trajectory = PredictedTrajectory::default();
+        }
+        return;
+    }
+    let (Ok((transform, velocity)), Ok((thrust, fuel))) =
+        (body.get_single(), rocket_query.get_single())
+    else {
+        return;
+    };
+
+    // the engine only pushes while there is fuel, as in the physics
+    let thrust_force = if fuel.value > 0.0 {
+        transform.rotation * Vec3::Y * thrust.value
+    } else {
+        Vec3::ZERO
+    };
+    let wind_force: Vec3 = wind_query
+        .iter()
+        .map(|(direction, speed)| direction.value * speed.value)
+        .sum();
+
+    *trajectory = predict_trajectory(
+        &rapier_context,
+        &gravity,
+        transform.translation,
+        velocity.linvel,
+        (thrust_force + wind_force) / ROCKET_MASS,
+    );
+}
+
+/// Path coloured by how the touchdown would go, judged on speed alone.
+fn draw_trajectory_system(
+    mission: Res<ActiveMission>,
+    trajectory: Res<PredictedTrajectory>,
+    mut gizmos: Gizmos,
+) {
+    if trajectory.points.len() < 2 {
+        return;
+    }
+
+    let color = trajectory.impact.map_or(PATH_COLOR_MISS, |impact| {
+        ApproachSafety::assess(impact.speed, 0.0, &mission.0.success).color()
+    });
+    gizmos.linestrip(trajectory.points.iter().copied(), color);
+}
+
+fn clear_trajectory(mut trajectory: ResMut<PredictedTrajectory>) {
+    *trajectory = PredictedTrajectory::default();
+}
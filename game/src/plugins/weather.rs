@@ -1,15 +1,67 @@
 use bevy::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 
+use super::{
+    difficulty::ActiveDifficulty,
+    ground_control::GroundCommand,
+    mission::{ActiveMission, MissionStarted},
+    rocket::MissionTime,
+    splash::GameState,
+};
+
 #[derive(Component, Default)]
 pub struct WindDirection {
     pub value: Vec3,
 }
 
+/// `value` is the wind in kph divided by 10, applied directly as a force.
 #[derive(Component, Default)]
 pub struct WindSpeed {
     pub value: f32,
 }
+
+impl WindSpeed {
+    pub fn meters_per_second(&self) -> f32 {
+        self.value * 10.0 / 3.6
+    }
+}
+/// Wind that swells and fades on top of a steady speed.
+#[derive(Resource, Default)]
+struct WindGusts {
+    base_speed: f32,
+    gust_speed: f32,
+    period: f32,
+}
+
+/// Visibility the weather service reports in clear air.
+const CLEAR_VISIBILITY_KM: f32 = 10.0;
+// wind strong enough to start lifting dust, and to make a full storm
+const DUST_LIFT_KPH: f32 = 30.0;
+const DUST_STORM_KPH: f32 = 90.0;
+
+/// Airborne dust, from 0 for clear air to 1 for a full storm.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct Dust {
+    pub opacity: f32,
+}
+
+impl Dust {
+    /// Dust seen by the weather service, from poor visibility or wind strong
+    /// enough to raise it.
+    pub fn from_weather(visibility_km: f32, wind_kph: f32) -> Self {
+        let haze = 1.0 - visibility_km / CLEAR_VISIBILITY_KM;
+        let lift = (wind_kph - DUST_LIFT_KPH) / (DUST_STORM_KPH - DUST_LIFT_KPH);
+        Self {
+            opacity: haze.max(lift).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Random error for a sensor reading, up to `scale` in a full storm.
+    pub fn sensor_noise(&self, scale: f32) -> f32 {
+        (rand::random::<f32>() - rand::random::<f32>()) * scale * self.opacity
+    }
+}
+
 #[derive(Bundle)]
 struct WeatherBundle {
     wind_direction: WindDirection,
@@ -21,13 +73,16 @@ pub struct Current {
     temp_c: f32,
     wind_kph: f32,
     wind_degree: f32,
+    #[serde(default)]
+    vis_km: Option<f32>,
 }
 
 #[derive(Resource)]
-struct CurrentWeather {
-    temp_c: f32,
-    wind_kph: f32,
-    wind_degree: f32,
+pub struct CurrentWeather {
+    pub temp_c: f32,
+    pub wind_kph: f32,
+    pub wind_degree: f32,
+    pub visibility_km: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -80,8 +135,19 @@ impl Plugin for WeatherPlugin {
             temp_c: self.weather.current.temp_c,
             wind_kph: self.weather.current.wind_kph,
             wind_degree: self.weather.current.wind_degree,
+            visibility_km: self.weather.current.vis_km.unwrap_or(CLEAR_VISIBILITY_KM),
         })
-        .add_systems(Startup, setup);
+        .init_resource::<WindGusts>()
+        .init_resource::<Dust>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (apply_mission_wind_system, ground_wind_system).chain(),
+        )
+        .add_systems(
+            Update,
+            wind_gust_system.run_if(in_state(GameState::Playing)),
+        );
     }
 }
 
@@ -101,3 +167,81 @@ fn setup(mut commands: Commands, current_weather: ResMut<CurrentWeather>) {
         wind_speed: WindSpeed { value: wind_speed },
     });
 }
+
+fn apply_mission_wind_system(
+    mut mission_started: EventReader<MissionStarted>,
+    mission: Res<ActiveMission>,
+    difficulty: Res<ActiveDifficulty>,
+    current_weather: Res<CurrentWeather>,
+    mut gusts: ResMut<WindGusts>,
+    mut dust: ResMut<Dust>,
+    mut weather: Query<(&mut WindDirection, &mut WindSpeed)>,
+) {
+    if mission_started.read().count() == 0 {
+        return;
+    }
+
+    *dust = match mission.0.wind {
+        Some(wind) => Dust {
+            opacity: wind.dust_opacity.clamp(0.0, 1.0),
+        },
+        None => Dust::from_weather(current_weather.visibility_km, current_weather.wind_kph),
+    };
+
+    // without a scripted wind the live weather is used
+    let (speed_kph, degrees) = match mission.0.wind {
+        Some(wind) => (wind.speed_kph, wind.degrees),
+        None => (current_weather.wind_kph, current_weather.wind_degree),
+    };
+    let gust = mission.0.wind.unwrap_or_default();
+    let strength = difficulty.0.wind_multiplier / 10.0;
+    *gusts = WindGusts {
+        base_speed: speed_kph * strength,
+        gust_speed: gust.gust_kph * strength,
+        period: gust.gust_period,
+    };
+
+    for (mut wind_direction, mut wind_speed) in weather.iter_mut() {
+        wind_direction.value = convert_degrees_to_vec3(degrees);
+        wind_speed.value = gusts.base_speed;
+    }
+}
+
+fn wind_gust_system(
+    gusts: Res<WindGusts>,
+    mission_time: Res<MissionTime>,
+    mut weather: Query<&mut WindSpeed>,
+) {
+    if gusts.gust_speed <= 0.0 || gusts.period <= 0.0 {
+        return;
+    }
+
+    let phase = mission_time.seconds / gusts.period * std::f32::consts::TAU;
+    for mut wind_speed in weather.iter_mut() {
+        wind_speed.value = gusts.base_speed + gusts.gust_speed * (0.5 - 0.5 * phase.cos());
+    }
+}
+
+fn ground_wind_system(
+    mut ground_commands: EventReader<GroundCommand>,
+    mut gusts: ResMut<WindGusts>,
+    mut dust: ResMut<Dust>,
+    mut weather: Query<(&mut WindDirection, &mut WindSpeed)>,
+) {
+    for command in ground_commands.read() {
+        if let GroundCommand::SetDust { opacity } = command {
+            dust.opacity = *opacity;
+        }
+        if let GroundCommand::SetWind { speed_kph, degrees } = command {
+            // a ground station sets a steady wind
+            *gusts = WindGusts {
+                base_speed: speed_kph / 10.0,
+                ..default()
+            };
+            for (mut wind_direction, mut wind_speed) in weather.iter_mut() {
+                wind_direction.value = convert_degrees_to_vec3(*degrees);
+                wind_speed.value = speed_kph / 10.0;
+            }
+        }
+    }
+}
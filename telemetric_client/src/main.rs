@@ -1,22 +1,58 @@
 use std::error::Error;
-use std::mem;
 use bincode;
 use serde::Deserialize;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 use bevy::prelude::*;
 
+const TELEMETRY_SCHEMA_VERSION: u16 = 5;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum GameState {
+    Splash,
+    MainMenu,
+    Briefing,
+    Playing,
+    Paused,
+    Landed,
+    Crashed,
+    Results,
+    Settings,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum FlightEvent {
+    Touchdown { speed: f32 },
+    Liftoff,
+    Flameout,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct TelemetryData {
+    pub schema_version: u16,
+    pub sequence: u64,
+    pub dropped_samples: u64,
+    pub mission_time: f32,
+    pub game_state: GameState,
     pub fuel: f32,
     pub altitude: f32,
+    pub radar_altitude: Option<f32>,
+    pub terrain_slope: f32,
+    pub time_to_impact: Option<f32>,
+    pub position: Vec3,
     pub velocity: Vec3,
+    pub attitude: Quat,
+    pub attitude_euler: Vec3,
+    pub angular_velocity: Vec3,
     pub thrust: f32,
     pub left_ecs: f32,
     pub right_ecs: f32,
     pub wind_speed: f32,
     pub wind_direction: Vec3,
+    pub temp_c: f32,
+    pub dust_opacity: f32,
+    pub events: Vec<FlightEvent>,
 }
 
 fn format_vec3(vec: Vec3) -> String {
@@ -26,26 +62,72 @@ fn format_vec3(vec: Vec3) -> String {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let mut stream = TcpStream::connect("127.0.0.1:8088").await?;
-    let mut buffer = [0; mem::size_of::<TelemetryData>()];
+
+    // optional strategy for when we fall behind: drop_oldest, latest_only or block
+    if let Some(backpressure) = std::env::args().nth(1) {
+        stream
+            .write_all(format!("backpressure {}\n", backpressure).as_bytes())
+            .await?;
+    }
+
+    let mut buffer = Vec::new();
+    let mut events = Vec::new();
 
     loop {
-        let n = stream.read(&mut buffer).await?;
-        if n == 0 {
-            break;
+        let length = match stream.read_u32_le().await {
+            Ok(length) => length as usize,
+            Err(_) => break,
+        };
+        buffer.resize(length, 0);
+        stream.read_exact(&mut buffer).await?;
+
+        // the schema version always comes first, so check it before decoding the rest
+        let schema_version: u16 = bincode::deserialize(&buffer)?;
+        if schema_version != TELEMETRY_SCHEMA_VERSION {
+            return Err(format!(
+                "unsupported telemetry schema {}, expected {}",
+                schema_version, TELEMETRY_SCHEMA_VERSION
+            )
+            .into());
         }
 
         let telemetry: TelemetryData = bincode::deserialize(&buffer).unwrap();
+        for event in &telemetry.events {
+            events.push(format!("T+{:.1}s {:?}", telemetry.mission_time, event));
+        }
+
         // clear screen
         print!("\x1B[2J\x1B[1;1H");
         // print telemetry data line by line
+        println!("Sample: {}", telemetry.sequence);
+        println!("Dropped samples: {}", telemetry.dropped_samples);
+        println!("Mission time: {:.1}", telemetry.mission_time);
+        println!("State: {:?}", telemetry.game_state);
         println!("Fuel: {}", telemetry.fuel);
         println!("Altitude: {}", telemetry.altitude);
+        match telemetry.radar_altitude {
+            Some(height) => println!("Radar altitude: {:.2}", height),
+            None => println!("Radar altitude: out of range"),
+        }
+        println!("Terrain slope: {:.1}", telemetry.terrain_slope);
+        if let Some(seconds) = telemetry.time_to_impact {
+            println!("Time to impact: {:.1}s", seconds);
+        }
+        println!("Position: {:?}", format_vec3(telemetry.position));
         println!("Velocity: {:?}", format_vec3(telemetry.velocity));
+        println!("Attitude: {:?}", format_vec3(telemetry.attitude_euler));
+        println!("Angular velocity: {:?}", format_vec3(telemetry.angular_velocity));
         println!("Thrust: {}", telemetry.thrust);
         println!("Left ECS: {}", telemetry.left_ecs);
         println!("Right ECS: {}", telemetry.right_ecs);
         println!("Wind Speed: {}", telemetry.wind_speed);
         println!("Wind Direction: {:?}", telemetry.wind_direction);
+        println!("Temperature: {}", telemetry.temp_c);
+        println!("Dust: {:.0}%", telemetry.dust_opacity * 100.0);
+        println!("Events:");
+        for event in events.iter().rev().take(5) {
+            println!("  {}", event);
+        }
     }
 
     Ok(())